@@ -15,12 +15,9 @@ fn schema_to_rust(schema_file: &str, out_dir: &str, out_file: &str) {
 
     // Add a title so that typify knows what to name the struct.
     {
-        match &schema.schema.metadata {
-            Some(_metadata) => {
-                panic!("Schema metadata is not missing. Apparently the schema changed. Update build.rs to handle the new schema.");
-            }
-            None {} => {}
-        };
+        if schema.schema.metadata.is_some() {
+            panic!("Schema metadata is not missing. Apparently the schema changed. Update build.rs to handle the new schema.");
+        }
         schema.schema.metadata = Some(Box::new(schemars::schema::Metadata {
             title: Some("Catalog".to_string()),
             ..Default::default()
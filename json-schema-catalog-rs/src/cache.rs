@@ -0,0 +1,225 @@
+//! An on-disk cache of the [`Index`].
+//!
+//! Building the index means parsing every catalog, which adds up when many
+//! catalogs are installed and a script looks up schemas one at a time. The cache
+//! records the path, modification time and size of every catalog file that went
+//! into the index, including the included ones, and is only used while none of
+//! them have changed.
+//!
+//! Each set of catalog files gets its own cache file, and only the most recently
+//! written ones are kept. The catalog files should be absolute paths, so that the
+//! index doesn't depend on the current directory.
+
+use crate::error::{Error, Result};
+use crate::{CatalogFile, Index, IndexEntry, PrefixEntry};
+use sha2::Digest;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Bumped when the format of the cache changes.
+const VERSION: u32 = 2;
+
+/// How many cache files, for as many sets of catalog files, are kept.
+const MAX_FILES: usize = 16;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CachedIndex {
+    version: u32,
+    /// The catalog files that were asked for.
+    files: Vec<PathBuf>,
+    /// All the catalog files that were read.
+    stamps: Vec<Stamp>,
+    by_id: HashMap<String, IndexEntry>,
+    prefixes: Vec<PrefixEntry>,
+}
+
+#[derive(PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Stamp {
+    path: PathBuf,
+    size: u64,
+    /// Seconds and nanoseconds since the Unix epoch, if the platform has them.
+    modified: Option<(u64, u32)>,
+}
+impl Stamp {
+    fn of(path: &Path) -> std::io::Result<Stamp> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| (since.as_secs(), since.subsec_nanos()));
+        Ok(Stamp {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// The file in the cache directory `dir` for the index of the catalog `files`.
+///
+/// Each set of catalog files gets its own file, so that switching between projects
+/// doesn't invalidate the cache.
+pub fn cache_file(dir: &Path, files: &[PathBuf]) -> PathBuf {
+    let mut hasher = sha2::Sha256::new();
+    for path in files {
+        hasher.update(path.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+    }
+    let hash: String = hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    dir.join(format!("index-{}.json", hash))
+}
+
+/// Read the index of the catalog `files` from `cache_file`. Returns `None` if there
+/// is no usable cache, or if any of the catalog files changed since it was written.
+///
+/// Only the entries are cached; the policies of the returned index are the defaults.
+pub fn load(cache_file: &Path, files: &[PathBuf]) -> Option<Index> {
+    let content = std::fs::read(cache_file).ok()?;
+    let cached: CachedIndex = serde_json::from_slice(&content).ok()?;
+    if cached.version != VERSION || cached.files != files {
+        return None;
+    }
+    for stamp in &cached.stamps {
+        if Stamp::of(&stamp.path).ok()? != *stamp {
+            return None;
+        }
+    }
+    Some(Index {
+        by_id: cached.by_id,
+        prefixes: cached.prefixes,
+        ..Index::new()
+    })
+}
+
+/// Write the index built from `catalogs`, which were read from `files`, to
+/// `cache_file`. The file is replaced atomically, so that concurrent readers never
+/// see a partial cache. Then all but the [`MAX_FILES`] most recently written cache
+/// files in its directory are removed.
+pub fn store(
+    cache_file: &Path,
+    files: &[PathBuf],
+    catalogs: &[CatalogFile],
+    index: &Index,
+) -> Result<()> {
+    let stamps = catalogs
+        .iter()
+        .map(|catalog| {
+            Stamp::of(&catalog.file).map_err(|source| Error::Access {
+                path: catalog.file.clone(),
+                source,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let cached = CachedIndex {
+        version: VERSION,
+        files: files.to_vec(),
+        stamps,
+        by_id: index.by_id.clone(),
+        prefixes: index.prefixes.clone(),
+    };
+    let content = serde_json::to_vec(&cached).map_err(|source| Error::Serialize {
+        format: crate::format::DocumentFormat::Json,
+        source: source.into(),
+    })?;
+
+    let write_error = |source| Error::Write {
+        path: cache_file.to_path_buf(),
+        source,
+    };
+    if let Some(dir) = cache_file.parent() {
+        std::fs::create_dir_all(dir).map_err(write_error)?;
+    }
+    let temporary = cache_file.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&temporary, content).map_err(write_error)?;
+    std::fs::rename(&temporary, cache_file).map_err(|source| {
+        let _ = std::fs::remove_file(&temporary);
+        write_error(source)
+    })?;
+    if let Some(dir) = cache_file.parent() {
+        // Other processes may be pruning at the same time, so errors are ignored
+        let _ = prune(dir);
+    }
+    Ok(())
+}
+
+/// Remove all but the [`MAX_FILES`] most recently written cache files in `dir`.
+fn prune(dir: &Path) -> std::io::Result<()> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("index-") && name.ends_with(".json") {
+            files.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, file) in files.into_iter().skip(MAX_FILES) {
+        let _ = std::fs::remove_file(file);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_catalogs;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("cache");
+        let write_catalog = |location: &str| {
+            dir.write(
+                "catalog.json",
+                &format!(
+                    r#"{{"name": "Cached", "groups": [{{"name": "Cached", "baseLocation": ".",
+                        "schemas": [{{"id": "https://example.com/a.json", "location": "{}"}}]}}]}}"#,
+                    location
+                ),
+            )
+        };
+        let catalog = write_catalog("a.json");
+        let files = vec![catalog.clone()];
+        let cache_file = cache_file(&dir.join("cache"), &files);
+        assert!(load(&cache_file, &files).is_none());
+
+        let catalogs = read_catalogs(&files).unwrap();
+        let mut index = Index::new();
+        for catalog in &catalogs {
+            catalog.catalog.index(catalog.dir(), &mut index);
+        }
+        store(&cache_file, &files, &catalogs, &index).unwrap();
+        let cached = load(&cache_file, &files).unwrap();
+        assert_eq!(
+            cached.get_path("https://example.com/a.json").unwrap(),
+            dir.join("a.json")
+        );
+        assert!(load(&cache_file, &[]).is_none());
+
+        write_catalog("other.json");
+        assert!(load(&cache_file, &files).is_none());
+    }
+
+    #[test]
+    fn pruning() {
+        let dir = TempDir::new("cache-pruning");
+        let epoch = std::time::UNIX_EPOCH;
+        for i in 0..MAX_FILES + 2 {
+            let file = std::fs::File::create(dir.join(format!("index-{}.json", i))).unwrap();
+            file.set_modified(epoch + std::time::Duration::from_secs(i as u64))
+                .unwrap();
+        }
+        dir.write("other.json", "");
+        prune(&dir).unwrap();
+        assert!(!dir.join("index-0.json").exists());
+        assert!(!dir.join("index-1.json").exists());
+        assert!(dir.join("index-2.json").exists());
+        assert!(dir.join(format!("index-{}.json", MAX_FILES + 1)).exists());
+        assert!(dir.join("other.json").exists());
+    }
+}
@@ -0,0 +1,169 @@
+//! Per-project configuration, read from a `json-schema-catalog.toml` file.
+//!
+//! ```toml
+//! catalogs = ["schemas/catalog.json"]
+//! xdg = false
+//! integrity = "require"
+//!
+//! [replace]
+//! ignore-unknown = true
+//! keywords = ["$ref", "$schema"]
+//! document = "schema"
+//! output = "in-place"
+//!
+//! [check]
+//! schema-id = false
+//!
+//! [paths]
+//! confine = true
+//! max-file-size = 10000000
+//! ```
+//!
+//! Settings that are absent are left to the defaults of the command line interface.
+
+use crate::error::{Error, Result};
+use crate::format::DocumentFormat;
+use crate::references::DocumentKind;
+use crate::{IntegrityPolicy, PathPolicy};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The name of the configuration file.
+pub const CONFIG_FILE_NAME: &str = "json-schema-catalog.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Catalog files for the project. When read with [`Config::read`], these are
+    /// resolved relative to the directory of the configuration file.
+    #[serde(default)]
+    pub catalogs: Vec<PathBuf>,
+    /// Whether to use the catalogs in the XDG data directories.
+    pub xdg: Option<bool>,
+    /// How to treat the `integrity` of schemas when resolving them.
+    pub integrity: Option<IntegrityPolicy>,
+    #[serde(default)]
+    pub replace: ReplaceConfig,
+    #[serde(default)]
+    pub check: CheckConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+}
+
+/// Defaults for the `replace` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ReplaceConfig {
+    /// Ignore URIs for which no schema can be found.
+    pub ignore_unknown: Option<bool>,
+    /// The object keys whose values are replaced.
+    pub keywords: Option<Vec<String>>,
+    /// Where references are looked for. Detected from each document if absent.
+    pub document: Option<DocumentKind>,
+    /// Where the results are written. Conflicts with `output-dir`.
+    pub output: Option<OutputMode>,
+    /// The directory that the results are written into. When read with
+    /// [`Config::read`], this is resolved relative to the directory of the
+    /// configuration file.
+    pub output_dir: Option<PathBuf>,
+}
+
+/// Where `replace` writes its results, like `--in-place` and `--check` do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputMode {
+    Stdout,
+    InPlace,
+    Check,
+}
+
+/// The checks performed by the `check` command. Only the id check can be
+/// configured here; the files that `check` refuses follow [`PathsConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CheckConfig {
+    /// Check that ids in schema files match the catalog. See [`crate::CheckOptions`].
+    pub schema_id: Option<bool>,
+}
+
+/// The [`PathPolicy`] for the files that catalogs point to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PathsConfig {
+    /// See [`PathPolicy::confine`].
+    pub confine: Option<bool>,
+    /// See [`PathPolicy::max_file_size`].
+    pub max_file_size: Option<u64>,
+}
+impl PathsConfig {
+    pub fn path_policy(&self) -> PathPolicy {
+        PathPolicy {
+            confine: self.confine.unwrap_or(false),
+            max_file_size: self.max_file_size,
+        }
+    }
+}
+
+impl Config {
+    /// Read a configuration file.
+    pub fn read(file: &Path) -> Result<Config> {
+        let content = std::fs::read_to_string(file).map_err(|source| Error::Read {
+            path: file.to_path_buf(),
+            source,
+        })?;
+        let mut config: Config = toml::from_str(&content).map_err(|e| Error::Parse {
+            path: file.to_path_buf(),
+            format: DocumentFormat::Toml,
+            source: Box::new(e),
+        })?;
+        let dir = file.parent().unwrap_or(Path::new(""));
+        for catalog in &mut config.catalogs {
+            *catalog = dir.join(&*catalog);
+        }
+        if let Some(output_dir) = &mut config.replace.output_dir {
+            *output_dir = dir.join(&*output_dir);
+        }
+        Ok(config)
+    }
+
+    /// Find the configuration file in `start` or the nearest of its ancestors.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|file| file.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_and_read() {
+        let file = Config::find(Path::new("test/project/sub")).unwrap();
+        assert_eq!(file, Path::new("test/project").join(CONFIG_FILE_NAME));
+        assert_eq!(
+            Config::read(&file).unwrap(),
+            Config {
+                catalogs: vec![PathBuf::from("test/project/../example.yaml")],
+                xdg: Some(false),
+                integrity: Some(IntegrityPolicy::Verify),
+                replace: ReplaceConfig {
+                    ignore_unknown: Some(true),
+                    keywords: Some(vec!["$ref".to_string(), "$schema".to_string()]),
+                    document: Some(DocumentKind::Schema),
+                    output: Some(OutputMode::Check),
+                    output_dir: None,
+                },
+                check: CheckConfig {
+                    schema_id: Some(false),
+                },
+                paths: PathsConfig {
+                    confine: None,
+                    max_file_size: Some(1000000),
+                },
+            }
+        );
+    }
+}
@@ -0,0 +1,630 @@
+//! Writing changed string values back into the text of a document.
+//!
+//! Serializing a changed document from scratch loses its key order, indentation,
+//! comments and number formatting. [`update_text`] instead replaces just the
+//! strings that changed in the original text, or appends the items that were added
+//! to arrays, and leaves everything else alone.
+
+use crate::format::DocumentFormat;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A string value that differs between the original and the updated document.
+struct Change<'a> {
+    pointer: String,
+    old: &'a str,
+    new: &'a str,
+}
+
+/// Items added to the end of an array.
+struct Append<'a> {
+    pointer: String,
+    items: &'a [Value],
+}
+
+/// Rewrite `content`, which parses as `original`, so that it parses as `updated`,
+/// by replacing the string values that differ between the two, and appending the
+/// items that were added to the end of arrays.
+///
+/// Returns `None` if that is not possible: when the documents differ in other ways,
+/// when a string could not be located in the text, as for YAML in flow style, or
+/// when items are appended to anything but a top-level array in YAML and TOML, or
+/// strings are changed in TOML. The document has to be serialized from scratch then.
+pub fn update_text(
+    format: DocumentFormat,
+    content: &str,
+    original: &Value,
+    updated: &Value,
+) -> Option<String> {
+    let mut changes = vec![];
+    let mut appends = vec![];
+    if !diff(
+        original,
+        updated,
+        &mut String::new(),
+        &mut changes,
+        &mut appends,
+    ) {
+        return None;
+    }
+    if changes.is_empty() && appends.is_empty() {
+        return Some(content.to_string());
+    }
+    let text = match format {
+        DocumentFormat::Json | DocumentFormat::Jsonc => update_json(content, &changes, &appends)?,
+        DocumentFormat::Yaml => append_yaml(&update_yaml(content, &changes)?, &appends)?,
+        DocumentFormat::Toml if changes.is_empty() => append_toml(content, &appends)?,
+        DocumentFormat::Toml => return None,
+    };
+    // The text was edited without fully understanding it, so verify the result
+    (format.parse(&text).ok()? == *updated).then_some(text)
+}
+
+/// Collect the string values that differ and the items appended to arrays.
+/// Returns false if anything else differs.
+fn diff<'a>(
+    original: &'a Value,
+    updated: &'a Value,
+    pointer: &mut String,
+    changes: &mut Vec<Change<'a>>,
+    appends: &mut Vec<Append<'a>>,
+) -> bool {
+    let mut visit = |key: &str, original, updated, changes: &mut _, appends: &mut _| {
+        let len = pointer.len();
+        pointer.push('/');
+        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        let same = diff(original, updated, pointer, changes, appends);
+        pointer.truncate(len);
+        same
+    };
+    match (original, updated) {
+        (Value::String(old), Value::String(new)) => {
+            if old != new {
+                changes.push(Change {
+                    pointer: pointer.clone(),
+                    old,
+                    new,
+                });
+            }
+            true
+        }
+        (Value::Object(original), Value::Object(updated)) => {
+            original.len() == updated.len()
+                && original.iter().all(|(key, original)| {
+                    updated
+                        .get(key)
+                        .is_some_and(|updated| visit(key, original, updated, changes, appends))
+                })
+        }
+        (Value::Array(original), Value::Array(updated)) => {
+            let same = original.len() <= updated.len()
+                && original
+                    .iter()
+                    .zip(updated)
+                    .enumerate()
+                    .all(|(i, (original, updated))| {
+                        visit(&i.to_string(), original, updated, changes, appends)
+                    });
+            if same && original.len() < updated.len() {
+                appends.push(Append {
+                    pointer: pointer.clone(),
+                    items: &updated[original.len()..],
+                });
+            }
+            same
+        }
+        (original, updated) => original == updated,
+    }
+}
+
+/// Replace the changed strings in JSON or JSONC text, located by their pointers,
+/// and append the new array items in the indentation style of the text.
+fn update_json(content: &str, changes: &[Change], appends: &[Append]) -> Option<String> {
+    use jsonc_parser::ast;
+    use jsonc_parser::common::Ranged;
+
+    struct Edits<'a> {
+        content: &'a str,
+        changes: HashMap<&'a str, &'a str>,
+        appends: HashMap<&'a str, &'a [Value]>,
+        edits: Vec<(usize, usize, String)>,
+    }
+
+    fn find(value: &ast::Value, pointer: &mut String, e: &mut Edits) {
+        let mut visit = |key: &str, value, e: &mut Edits| {
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            find(value, pointer, e);
+            pointer.truncate(len);
+        };
+        match value {
+            ast::Value::StringLit(string) => {
+                if let Some(new) = e.changes.get(pointer.as_str()) {
+                    let new = serde_json::to_string(new).unwrap_or_default();
+                    e.edits.push((string.range.start, string.range.end, new));
+                }
+            }
+            ast::Value::Object(object) => {
+                for property in &object.properties {
+                    visit(property.name.as_str(), &property.value, e);
+                }
+            }
+            ast::Value::Array(array) => {
+                for (i, element) in array.elements.iter().enumerate() {
+                    visit(&i.to_string(), element, e);
+                }
+                if let Some(items) = e.appends.get(pointer.as_str()) {
+                    let unit = indent_unit(e.content);
+                    let edit = match array.elements.last() {
+                        // Arrays written on a single line stay on it
+                        Some(last) if !e.content[array.range.start..last.end()].contains('\n') => {
+                            let items = items.iter().map(|item| item.to_string());
+                            let text = std::iter::once(String::new()).chain(items);
+                            (last.end(), last.end(), text.collect::<Vec<_>>().join(", "))
+                        }
+                        Some(last) => {
+                            let indent = line_indent(e.content, last.start());
+                            let mut text = String::new();
+                            for item in *items {
+                                text.push_str(",\n");
+                                text.push_str(indent);
+                                text.push_str(&pretty_json(item, unit, indent));
+                            }
+                            (last.end(), last.end(), text)
+                        }
+                        None => {
+                            let outer = line_indent(e.content, array.range.start);
+                            let indent = format!("{}{}", outer, unit);
+                            let items = items
+                                .iter()
+                                .map(|item| {
+                                    format!("{}{}", indent, pretty_json(item, unit, &indent))
+                                })
+                                .collect::<Vec<_>>();
+                            let text = format!("[\n{}\n{}]", items.join(",\n"), outer);
+                            (array.range.start, array.range.end, text)
+                        }
+                    };
+                    e.edits.push(edit);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let parsed =
+        jsonc_parser::parse_to_ast(content, &Default::default(), &Default::default()).ok()?;
+    let mut e = Edits {
+        content,
+        changes: changes
+            .iter()
+            .map(|change| (change.pointer.as_str(), change.new))
+            .collect(),
+        appends: appends
+            .iter()
+            .map(|append| (append.pointer.as_str(), append.items))
+            .collect(),
+        edits: vec![],
+    };
+    find(&parsed.value?, &mut String::new(), &mut e);
+    Some(apply(content, e.edits))
+}
+
+/// The whitespace at the start of the line that contains `position`.
+fn line_indent(content: &str, position: usize) -> &str {
+    let start = content[..position].rfind('\n').map_or(0, |i| i + 1);
+    let line = &content[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// The indentation of the first indented line, or two spaces.
+fn indent_unit(content: &str) -> &str {
+    content
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+/// Pretty-print a value that starts at `indent`, indenting by `unit`.
+fn pretty_json(value: &Value, unit: &str, indent: &str) -> String {
+    use serde::Serialize;
+
+    let mut out = vec![];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    value.serialize(&mut serializer).unwrap_or_default();
+    String::from_utf8_lossy(&out).replace('\n', &format!("\n{}", indent))
+}
+
+/// The key of a pointer to a top-level value.
+fn top_level_key(pointer: &str) -> Option<String> {
+    let key = pointer.strip_prefix('/')?;
+    (!key.contains('/')).then(|| key.replace("~1", "/").replace("~0", "~"))
+}
+
+/// Append items to top-level block sequences in YAML text. The items are inserted
+/// after the last line that belongs to the sequence.
+fn append_yaml(content: &str, appends: &[Append]) -> Option<String> {
+    let mut edits = vec![];
+    for append in appends {
+        let key = top_level_key(&append.pointer)?;
+        let mut lines = content.split_inclusive('\n').scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        });
+        let (key_start, key_line) = lines.find(|(_, line)| {
+            !line.starts_with([' ', '\t', '#', '-'])
+                && yaml_key(line).is_some_and(|(k, value)| {
+                    k == key && (value.is_empty() || value.starts_with('#'))
+                })
+        })?;
+        let mut end = key_start + key_line.len();
+        let mut indent = None;
+        for (start, line) in lines {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let line_indent = &line[..line.len() - trimmed.len()];
+            if line_indent.is_empty() && !trimmed.starts_with("- ") {
+                break;
+            }
+            indent.get_or_insert(line_indent);
+            end = start + line.len();
+        }
+        let indent = indent?;
+        let items = serde_yaml_ng::to_string(append.items).ok()?;
+        let mut text = String::new();
+        if !content[..end].ends_with('\n') {
+            text.push('\n');
+        }
+        for line in items.lines() {
+            text.push_str(indent);
+            text.push_str(line);
+            text.push('\n');
+        }
+        edits.push((end, end, text));
+    }
+    Some(apply(content, edits))
+}
+
+/// Append items to top-level arrays of tables at the end of TOML text.
+fn append_toml(content: &str, appends: &[Append]) -> Option<String> {
+    let mut text = content.to_string();
+    for append in appends {
+        let key = top_level_key(&append.pointer)?;
+        let mut table = toml::Table::new();
+        table.insert(key, toml::Value::try_from(append.items).ok()?);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push('\n');
+        text.push_str(&toml::to_string(&table).ok()?);
+    }
+    Some(text)
+}
+
+/// A step on the way to a line of block-style YAML.
+enum Step {
+    Key(String),
+    Item(usize),
+}
+
+/// Replace the changed strings in block-style YAML text. The structure of the
+/// document is followed by the indentation of the lines, so that only the values
+/// at the pointers of the changes are replaced. Values that can't be located, such
+/// as those in flow style, are left alone, so the result has to be verified.
+fn update_yaml(content: &str, changes: &[Change]) -> Option<String> {
+    let changes: HashMap<&str, &Change> = changes
+        .iter()
+        .map(|change| (change.pointer.as_str(), change))
+        .collect();
+    let mut edits = vec![];
+    let mut offset = 0;
+    // The keys and sequence items that lead to the current line, with their columns
+    let mut path: Vec<(usize, Step)> = vec![];
+    // Lines that are indented further than this column belong to a block scalar
+    let mut block_scalar = None;
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let mut rest = text.trim_start_matches(' ');
+        let mut column = text.len() - rest.len();
+        if rest.is_empty() || block_scalar.is_some_and(|block| column > block) {
+            continue;
+        }
+        block_scalar = None;
+        if rest.starts_with('#') {
+            continue;
+        }
+        if rest.starts_with("---") || rest.starts_with("...") {
+            path.clear();
+            continue;
+        }
+        let mut parent = column;
+        while rest == "-" || rest.starts_with("- ") {
+            path.retain(|(c, _)| *c <= column);
+            let index = match path.last() {
+                Some(&(c, Step::Item(i))) if c == column => {
+                    path.pop();
+                    i + 1
+                }
+                _ => 0,
+            };
+            path.push((column, Step::Item(index)));
+            parent = column;
+            let item = rest[1..].trim_start_matches(' ');
+            column += rest.len() - item.len();
+            rest = item;
+        }
+        let Some((key, value)) = yaml_key(rest) else {
+            if rest.starts_with(['|', '>']) {
+                block_scalar = Some(parent);
+            }
+            continue;
+        };
+        path.retain(|(c, _)| *c < column);
+        path.push((column, Step::Key(key)));
+        if value.starts_with(['|', '>']) {
+            block_scalar = Some(column);
+            continue;
+        }
+        let Some(change) = changes.get(yaml_pointer(&path).as_str()) else {
+            continue;
+        };
+        let Some((len, style)) = yaml_scalar(value) else {
+            continue;
+        };
+        if decode_yaml_scalar(&value[..len], style).as_deref() == Some(change.old) {
+            let start = line_start + text.len() - value.len();
+            edits.push((start, start + len, encode_yaml_scalar(change.new, style)));
+        }
+    }
+    Some(apply(content, edits))
+}
+
+/// The JSON Pointer of the value at the end of `path`.
+fn yaml_pointer(path: &[(usize, Step)]) -> String {
+    let mut pointer = String::new();
+    for (_, step) in path {
+        pointer.push('/');
+        match step {
+            Step::Key(key) => pointer.push_str(&key.replace('~', "~0").replace('/', "~1")),
+            Step::Item(index) => pointer.push_str(&index.to_string()),
+        }
+    }
+    pointer
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScalarStyle {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+}
+
+/// The length and style of the scalar at the start of a YAML value, if it is a
+/// single-line string that can be replaced, followed by nothing but a comment.
+fn yaml_scalar(value: &str) -> Option<(usize, ScalarStyle)> {
+    let (len, style) = match value.chars().next()? {
+        '"' => (quoted_end(value, '"')?, ScalarStyle::DoubleQuoted),
+        '\'' => (quoted_end(value, '\'')?, ScalarStyle::SingleQuoted),
+        '|' | '>' | '&' | '*' | '!' | '[' | '{' | '#' => return None,
+        _ => {
+            let len = value.find(" #").unwrap_or(value.len());
+            (value[..len].trim_end().len(), ScalarStyle::Plain)
+        }
+    };
+    let comment = value[len..].trim();
+    if !(comment.is_empty() || comment.starts_with('#')) {
+        return None;
+    }
+    Some((len, style))
+}
+
+/// Split `key: value` text into the key and the text after the colon.
+fn yaml_key(text: &str) -> Option<(String, &str)> {
+    let (key, after_key) = match text.chars().next()? {
+        '"' => {
+            let end = quoted_end(text, '"')?;
+            (serde_json::from_str(&text[..end]).ok()?, &text[end..])
+        }
+        '\'' => {
+            let end = quoted_end(text, '\'')?;
+            (text[1..end - 1].replace("''", "'"), &text[end..])
+        }
+        _ => {
+            let end = text.find(": ").or_else(|| {
+                let key = text.trim_end();
+                key.ends_with(':').then(|| key.len() - 1)
+            })?;
+            (text[..end].trim_end().to_string(), &text[end..])
+        }
+    };
+    let value = after_key.trim_start().strip_prefix(':')?.trim_start();
+    Some((key, value))
+}
+
+/// The length of the quoted string at the start of `s`, including the quotes.
+fn quoted_end(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') {
+                chars.next();
+            } else {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+fn decode_yaml_scalar(text: &str, style: ScalarStyle) -> Option<String> {
+    match style {
+        ScalarStyle::Plain => Some(text.to_string()),
+        ScalarStyle::SingleQuoted => Some(text[1..text.len() - 1].replace("''", "'")),
+        ScalarStyle::DoubleQuoted => serde_json::from_str(text).ok(),
+    }
+}
+
+/// Write a string in the style of the value it replaces, if it can be.
+fn encode_yaml_scalar(value: &str, style: ScalarStyle) -> String {
+    let plain_ok = || {
+        serde_yaml_ng::from_str::<Value>(&format!("k: {}", value)).ok()
+            == Some(serde_json::json!({ "k": value }))
+    };
+    match style {
+        ScalarStyle::Plain if plain_ok() => value.to_string(),
+        ScalarStyle::SingleQuoted if !value.contains('\n') => {
+            format!("'{}'", value.replace('\'', "''"))
+        }
+        // JSON strings are valid double-quoted YAML scalars
+        _ => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+/// Replace byte ranges of `content`. The ranges must not overlap.
+fn apply(content: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    edits.sort_by_key(|&(start, _, _)| start);
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for (start, end, replacement) in edits {
+        result.push_str(&content[position..start]);
+        result.push_str(&replacement);
+        position = end;
+    }
+    result.push_str(&content[position..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn update(format: DocumentFormat, content: &str, f: impl FnOnce(&mut Value)) -> Option<String> {
+        let original = format.parse(content).unwrap();
+        let mut updated = original.clone();
+        f(&mut updated);
+        update_text(format, content, &original, &updated)
+    }
+
+    #[test]
+    fn json() {
+        let content = "{\n    \"type\": \"object\",\n    \"maximum\": 1.0e3,\n    \"properties\": { \"a\": {\"$ref\": \"a.json\"} },\n    \"$id\": \"x\"\n}";
+        assert_eq!(
+            update(DocumentFormat::Json, content, |v| {
+                v["properties"]["a"]["$ref"] = json!("file:///a \"quoted\".json");
+            })
+            .unwrap(),
+            "{\n    \"type\": \"object\",\n    \"maximum\": 1.0e3,\n    \"properties\": { \"a\": {\"$ref\": \"file:///a \\\"quoted\\\".json\"} },\n    \"$id\": \"x\"\n}"
+        );
+        assert_eq!(
+            update(
+                DocumentFormat::Jsonc,
+                "{\n  // comment\n  '$ref': 'a.json',\n}",
+                |v| {
+                    v["$ref"] = json!("b.json");
+                }
+            )
+            .unwrap(),
+            "{\n  // comment\n  '$ref': \"b.json\",\n}"
+        );
+        assert_eq!(
+            update(DocumentFormat::Json, content, |_| {}).unwrap(),
+            content
+        );
+        assert_eq!(
+            update(DocumentFormat::Json, content, |v| v["type"] = json!(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn yaml() {
+        let content = "# comment\nz: 1.50\nallOf:\n  - $ref: a.json # first\n  - '$ref': 'b.json'\nc:\n  $ref: \"c.json\"\n";
+        assert_eq!(
+            update(DocumentFormat::Yaml, content, |v| {
+                v["allOf"][0]["$ref"] = json!("file:///a.json");
+                v["allOf"][1]["$ref"] = json!("it's.json");
+                v["c"]["$ref"] = json!("#: yes");
+            })
+            .unwrap(),
+            "# comment\nz: 1.50\nallOf:\n  - $ref: file:///a.json # first\n  - '$ref': 'it''s.json'\nc:\n  $ref: \"#: yes\"\n"
+        );
+        assert_eq!(
+            update(DocumentFormat::Yaml, "a: {$ref: a.json}\n", |v| {
+                v["a"]["$ref"] = json!("b.json");
+            }),
+            None
+        );
+
+        // Only the value at the pointer is replaced, not the same string elsewhere
+        let content = "properties:\n  a:\n    $ref: a.json\n  b:\n    const:\n      $ref: a.json\n    description: |\n      $ref: a.json\nitems:\n- - $ref: a.json\n  - $ref: a.json # second\n";
+        assert_eq!(
+            update(DocumentFormat::Yaml, content, |v| {
+                v["properties"]["a"]["$ref"] = json!("b.json");
+                v["items"][0][1]["$ref"] = json!("b.json");
+            })
+            .unwrap(),
+            "properties:\n  a:\n    $ref: b.json\n  b:\n    const:\n      $ref: a.json\n    description: |\n      $ref: a.json\nitems:\n- - $ref: a.json\n  - $ref: b.json # second\n"
+        );
+    }
+
+    #[test]
+    fn append() {
+        let group = json!({"name": "b", "schemas": [{"id": "b"}]});
+        let add = |v: &mut Value| v["groups"].as_array_mut().unwrap().push(group.clone());
+        assert_eq!(
+            update(
+                DocumentFormat::Jsonc,
+                "{\n    // comment\n    \"groups\": [\n        {\"name\": \"a\", \"schemas\": []}\n    ]\n}\n",
+                add
+            )
+            .unwrap(),
+            "{\n    // comment\n    \"groups\": [\n        {\"name\": \"a\", \"schemas\": []},\n        {\n            \"name\": \"b\",\n            \"schemas\": [\n                {\n                    \"id\": \"b\"\n                }\n            ]\n        }\n    ]\n}\n"
+        );
+        assert_eq!(
+            update(DocumentFormat::Json, "{\"groups\": [1]}", add).unwrap(),
+            "{\"groups\": [1, {\"name\":\"b\",\"schemas\":[{\"id\":\"b\"}]}]}"
+        );
+        assert_eq!(
+            update(
+                DocumentFormat::Yaml,
+                "# comment\ngroups:\n  - name: a # first\n    schemas: []\n\nname: c\n",
+                add
+            )
+            .unwrap(),
+            "# comment\ngroups:\n  - name: a # first\n    schemas: []\n  - name: b\n    schemas:\n    - id: b\n\nname: c\n"
+        );
+        assert_eq!(
+            update(
+                DocumentFormat::Toml,
+                "# comment\nname = \"c\"\n\n[[groups]]\nname = \"a\"\nschemas = []\n",
+                add
+            )
+            .unwrap(),
+            "# comment\nname = \"c\"\n\n[[groups]]\nname = \"a\"\nschemas = []\n\n[[groups]]\nname = \"b\"\n\n[[groups.schemas]]\nid = \"b\"\n"
+        );
+        assert_eq!(
+            update(DocumentFormat::Json, "{\n  \"groups\": []\n}", |v| {
+                v["groups"] = json!([1, 2]);
+            })
+            .unwrap(),
+            "{\n  \"groups\": [\n    1,\n    2\n  ]\n}"
+        );
+        assert_eq!(
+            update(DocumentFormat::Json, "{\"groups\": [1, 2]}", |v| {
+                v["groups"] = json!([2, 1]);
+            }),
+            None
+        );
+    }
+}
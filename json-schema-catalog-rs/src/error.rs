@@ -0,0 +1,214 @@
+//! Errors returned by the catalog functions of this library.
+//!
+//! Errors that occur inside a catalog or group are wrapped in [`Error::InCatalog`],
+//! [`Error::IncludedFrom`] or [`Error::InGroup`], which record where the problem was
+//! found. Use [`Error::innermost`] to get at the underlying problem.
+
+use crate::format::DocumentFormat;
+use std::path::PathBuf;
+
+/// An error from a parser or serializer of one of the [`DocumentFormat`]s.
+pub type FormatError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not read {}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not write {}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not access {}", path.display())]
+    Access {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not parse {} as {format}", path.display())]
+    Parse {
+        path: PathBuf,
+        format: DocumentFormat,
+        source: FormatError,
+    },
+    /// The file has no known extension, and its content is not JSON, JSONC or YAML.
+    /// The source is the YAML error.
+    #[error("Could not parse {} as JSON, JSONC or YAML", path.display())]
+    UnrecognizedContent { path: PathBuf, source: FormatError },
+    #[error("Could not serialize as {format}")]
+    Serialize {
+        format: DocumentFormat,
+        source: FormatError,
+    },
+    #[error("Unknown format {name}, expected one of json, jsonc, yaml, toml")]
+    UnknownFormat { name: String },
+
+    /// The document is well-formed, but not a catalog.
+    #[error("Could not parse catalog {}", path.display())]
+    InvalidCatalog {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The files from the first one back to itself.
+    #[error("Catalog include cycle: {}", display_cycle(cycle))]
+    IncludeCycle { cycle: Vec<PathBuf> },
+    #[error("in catalog {name}, file {}", file.display())]
+    InCatalog {
+        name: String,
+        file: PathBuf,
+        source: Box<Error>,
+    },
+    #[error("included from catalog {name}, file {}", file.display())]
+    IncludedFrom {
+        name: String,
+        file: PathBuf,
+        source: Box<Error>,
+    },
+
+    #[error("Group name is empty")]
+    EmptyGroupName,
+    #[error("Group base location is empty")]
+    EmptyBaseLocation,
+    #[error("Group URI prefix is empty")]
+    EmptyUriPrefix,
+    #[error("Could not list files in {} for URI prefix {prefix}", dir.display())]
+    ListFiles {
+        dir: PathBuf,
+        prefix: String,
+        source: std::io::Error,
+    },
+    #[error("in catalog group {name}")]
+    InGroup { name: String, source: Box<Error> },
+
+    #[error("Schema id is empty")]
+    EmptySchemaId,
+    #[error("Schema location is empty")]
+    EmptySchemaLocation,
+    /// The `id` in a schema file differs from the id recorded in the catalog.
+    #[error("Recorded schema id {expected} does not match id {actual} in file {}", path.display())]
+    IdMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: serde_json::Value,
+    },
+    /// The content of a file does not match the `integrity` recorded in the catalog.
+    #[error("Integrity check failed for {}: expected {expected}, but the file has {actual}", path.display())]
+    IntegrityMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error("Integrity value {integrity} does not contain a supported algorithm (sha256, sha384, sha512)")]
+    UnsupportedIntegrity { integrity: String },
+    /// [`crate::IntegrityPolicy::Require`] is in effect, but the catalog has no hash for the schema.
+    #[error("No integrity recorded for schema {id}")]
+    MissingIntegrity { id: String },
+
+    #[error("Schema id {id} is not a valid URI")]
+    InvalidId { id: String, source: url::ParseError },
+    #[error("Schema with id {id} not found in catalog")]
+    SchemaNotFound { id: String },
+
+    /// A document could not be read as a JSON Schema.
+    #[error("Schema {} is not a valid JSON Schema", path.display())]
+    NotASchema {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A schema lacks a field that is needed to describe it in a catalog.
+    #[error("Schema {} does not have a {field} field", path.display())]
+    MissingSchemaField { path: PathBuf, field: &'static str },
+    #[error("Could not get the {part} of {}", path.display())]
+    InvalidPath { path: PathBuf, part: &'static str },
+    /// Catalogs are JSON documents, so the locations in them must be valid UTF-8.
+    #[error("Path {} is not valid UTF-8", path.display())]
+    NotUtf8 { path: PathBuf },
+
+    #[error("Location {location} is not supported: {reason}")]
+    InvalidLocation { location: String, reason: String },
+    /// Refused by [`crate::PathPolicy::confine`].
+    #[error("Location {} is absolute, which is not allowed by the path policy", path.display())]
+    AbsoluteLocation { path: PathBuf },
+    /// Refused by [`crate::PathPolicy::confine`].
+    #[error("Location {} is outside of the catalog directory {}", path.display(), root.display())]
+    OutsideRoot { path: PathBuf, root: PathBuf },
+    /// Refused by [`crate::PathPolicy::confine`].
+    #[error(
+        "Location {} resolves to {}, which is outside of the catalog directory {}",
+        path.display(),
+        target.display(),
+        root.display()
+    )]
+    SymlinkOutsideRoot {
+        path: PathBuf,
+        target: PathBuf,
+        root: PathBuf,
+    },
+    /// Refused by [`crate::PathPolicy::max_file_size`].
+    #[error("File {} is {size} bytes, more than the maximum of {max}", path.display())]
+    FileTooLarge { path: PathBuf, size: u64, max: u64 },
+
+    #[error("Could not download {url}")]
+    Download {
+        url: String,
+        source: Box<ureq::Error>,
+    },
+    /// The document is well-formed, but not a SchemaStore catalog.
+    #[error("Could not parse SchemaStore catalog {}", path.display())]
+    InvalidSchemaStoreCatalog {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("in SchemaStore entry {name}")]
+    InSchemaStoreEntry { name: String, source: Box<Error> },
+
+    #[error("Could not parse XML Catalog")]
+    ReadXml { source: quick_xml::Error },
+    #[error("Could not write XML Catalog")]
+    WriteXml { source: std::io::Error },
+    /// The XML is well-formed, but the entry is not allowed where it is.
+    #[error("Invalid XML Catalog entry {entry}: {reason}")]
+    InvalidXmlCatalog { entry: String, reason: String },
+
+    /// A reference keyword in a schema position, as found by
+    /// [`crate::references::find_references`], with another value than a string.
+    #[error("Expected a string for {keyword} at {pointer}, but got {value}")]
+    NonStringReference {
+        keyword: String,
+        pointer: String,
+        value: serde_json::Value,
+    },
+
+    #[error("Invalid proxy {proxy}")]
+    InvalidProxy {
+        proxy: String,
+        source: Box<ureq::Error>,
+    },
+}
+
+impl Error {
+    /// The underlying error, without the [`Error::InCatalog`], [`Error::IncludedFrom`],
+    /// [`Error::InGroup`] and [`Error::InSchemaStoreEntry`] wrappers.
+    pub fn innermost(&self) -> &Error {
+        match self {
+            Error::InCatalog { source, .. }
+            | Error::IncludedFrom { source, .. }
+            | Error::InGroup { source, .. }
+            | Error::InSchemaStoreEntry { source, .. } => source.innermost(),
+            e => e,
+        }
+    }
+}
+
+fn display_cycle(cycle: &[PathBuf]) -> String {
+    cycle
+        .iter()
+        .map(|f| f.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
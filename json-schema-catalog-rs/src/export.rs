@@ -0,0 +1,180 @@
+//! Export catalogs as schema associations for editors.
+//!
+//! Editors such as VS Code can't consume a JSON Schema Catalog directly, but they
+//! can be told where to find a schema and which files it applies to.
+
+use crate::error::{Error, Result};
+use crate::schemastore::{SchemaStoreCatalog, SchemaStoreEntry, SCHEMASTORE_CATALOG_SCHEMA};
+use crate::{Catalog, Index};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// A schema with its local file location and the files it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Association {
+    /// The canonical id of the schema.
+    pub id: String,
+    /// The name of the group that the schema is in.
+    pub name: String,
+    /// A `file://` URL of the schema, as resolved by the [`Index`].
+    pub url: url::Url,
+    /// Glob patterns of the files that the schema applies to.
+    pub file_match: Vec<String>,
+}
+
+/// Collect the associations for all schemas in the catalogs, ordered by id.
+///
+/// `file_matches` assigns glob patterns to schema ids. Schemas without patterns are
+/// included too; whether an editor can use them depends on the output format.
+/// `uriPrefix` groups are not included, as their ids are not known up front.
+pub fn associations<'a>(
+    catalogs: impl IntoIterator<Item = &'a Catalog>,
+    index: &Index,
+    file_matches: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Association>> {
+    let mut by_id = BTreeMap::new();
+    for catalog in catalogs {
+        for group in &catalog.groups {
+            for schema in &group.schemas {
+                let path =
+                    index
+                        .try_get_path(&schema.id)?
+                        .ok_or_else(|| Error::SchemaNotFound {
+                            id: schema.id.clone(),
+                        })?;
+                let path = std::path::absolute(&path).map_err(|source| Error::Access {
+                    path: path.clone(),
+                    source,
+                })?;
+                let url = url::Url::from_file_path(&path).map_err(|()| Error::InvalidPath {
+                    path: path.clone(),
+                    part: "file URL",
+                })?;
+                by_id.insert(
+                    schema.id.clone(),
+                    Association {
+                        id: schema.id.clone(),
+                        name: group.name.clone(),
+                        url,
+                        file_match: file_matches.get(&schema.id).cloned().unwrap_or_default(),
+                    },
+                );
+            }
+        }
+    }
+    Ok(by_id.into_values().collect())
+}
+
+/// VS Code settings that associate the schemas with JSON files.
+///
+/// Each schema is registered under its canonical id, as an inline schema that refers
+/// to the local file. Documents whose `$schema` is the id, and references to the id,
+/// then resolve to the local file, also for schemas without file patterns.
+pub fn vscode_json_settings(associations: &[Association]) -> serde_json::Value {
+    let schemas = associations
+        .iter()
+        .map(|a| {
+            let mut entry = json!({ "url": a.id, "schema": { "$ref": a.url.as_str() } });
+            if !a.file_match.is_empty() {
+                entry["fileMatch"] = json!(a.file_match);
+            }
+            entry
+        })
+        .collect::<Vec<_>>();
+    json!({ "json.schemas": schemas })
+}
+
+/// VS Code settings for the YAML extension that associate the schemas with YAML files.
+///
+/// The YAML extension only maps schemas to file patterns, so schemas without
+/// patterns are left out; see [`without_file_match`].
+pub fn vscode_yaml_settings(associations: &[Association]) -> serde_json::Value {
+    let schemas = associations
+        .iter()
+        .filter(|a| !a.file_match.is_empty())
+        .map(|a| (a.url.to_string(), json!(a.file_match)))
+        .collect::<serde_json::Map<_, _>>();
+    json!({ "yaml.schemas": schemas })
+}
+
+/// The associations that have no file patterns, which [`vscode_yaml_settings`]
+/// leaves out.
+pub fn without_file_match(associations: &[Association]) -> impl Iterator<Item = &Association> {
+    associations.iter().filter(|a| a.file_match.is_empty())
+}
+
+/// A SchemaStore catalog pointing to the local schema files.
+///
+/// The canonical id is recorded in the description, as SchemaStore has no field for it.
+pub fn schemastore_catalog(associations: &[Association]) -> SchemaStoreCatalog {
+    SchemaStoreCatalog {
+        schema: Some(SCHEMASTORE_CATALOG_SCHEMA.to_string()),
+        version: 1,
+        schemas: associations
+            .iter()
+            .map(|a| SchemaStoreEntry {
+                name: a.name.clone(),
+                description: Some(a.id.clone()),
+                url: a.url.to_string(),
+                file_match: a.file_match.clone(),
+                versions: BTreeMap::new(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn export_example() {
+        let catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let mut index = Index::new();
+        catalog.index(Path::new("test"), &mut index);
+        let id = "https://schema.example.com/schema/schema_catalog_schema.json";
+        let file_matches = BTreeMap::from([(id.to_string(), vec!["**/catalog*.json".to_string()])]);
+        let associations = associations([&catalog], &index, &file_matches).unwrap();
+        let url = url::Url::from_file_path(
+            std::path::absolute("vendor/schema_catalog_schema.json").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            associations,
+            vec![Association {
+                id: id.to_string(),
+                name: "json-schema-catalog-rs".to_string(),
+                url: url.clone(),
+                file_match: vec!["**/catalog*.json".to_string()],
+            }]
+        );
+        assert_eq!(
+            vscode_json_settings(&associations),
+            json!({ "json.schemas": [{
+                "fileMatch": ["**/catalog*.json"],
+                "url": id,
+                "schema": { "$ref": url.as_str() },
+            }] })
+        );
+        assert_eq!(
+            vscode_yaml_settings(&associations),
+            json!({ "yaml.schemas": { url.as_str(): ["**/catalog*.json"] } })
+        );
+
+        let associations = super::associations([&catalog], &index, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            vscode_json_settings(&associations),
+            json!({ "json.schemas": [{ "url": id, "schema": { "$ref": url.as_str() } }] })
+        );
+        assert_eq!(
+            vscode_yaml_settings(&associations),
+            json!({ "yaml.schemas": {} })
+        );
+        assert_eq!(without_file_match(&associations).count(), 1);
+        assert_eq!(
+            schemastore_catalog(&associations).schemas[0].description,
+            Some(id.to_string())
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Download schemas and the schemas they reference over HTTP(S).
+//!
+//! This works like [`crate::vendor`], but the schemas are downloaded from their
+//! ids instead of being looked up in catalogs. Downloads can be redirected to a
+//! mirror with [`Client::add_mirror`], and go through a proxy if one is configured.
+
+use crate::error::{Error, Result};
+use crate::vendor::{copy_closure, Document, Vendored};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Downloads schema documents.
+pub struct Client {
+    agent: ureq::Agent,
+    mirrors: Vec<(String, Url)>,
+}
+
+impl Client {
+    /// Create a client, going through a proxy such as `http://proxy.example.com:3128`.
+    ///
+    /// Without a proxy, the `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` environment
+    /// variables are used, like most HTTP clients do.
+    pub fn new(proxy: Option<&str>) -> Result<Client> {
+        let mut config = ureq::Agent::config_builder().http_status_as_error(false);
+        if let Some(proxy) = proxy {
+            let proxy = ureq::Proxy::new(proxy).map_err(|source| Error::InvalidProxy {
+                proxy: proxy.to_string(),
+                source: Box::new(source),
+            })?;
+            config = config.proxy(Some(proxy));
+        }
+        let config = config.build();
+        Ok(Client {
+            agent: ureq::Agent::new_with_config(config),
+            mirrors: vec![],
+        })
+    }
+
+    /// Download ids that start with `prefix` from `base` instead, keeping the rest
+    /// of the id. When several prefixes match, the longest one is used.
+    ///
+    /// The ids in the resulting catalog are not affected.
+    pub fn add_mirror(&mut self, prefix: String, base: Url) {
+        self.mirrors.push((prefix, base));
+    }
+
+    /// The URL to download an id from.
+    fn download_url(&self, url: &Url) -> Url {
+        self.mirrors
+            .iter()
+            .filter(|(prefix, _)| url.as_str().starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .and_then(|(prefix, base)| {
+                let rest = &url.as_str()[prefix.len()..];
+                Url::parse(&format!("{}{}", base, rest)).ok()
+            })
+            .unwrap_or_else(|| url.clone())
+    }
+
+    /// Download a document. Returns `None` if the server doesn't have it, or if
+    /// the URL is not an HTTP(S) URL.
+    pub fn get(&self, url: &Url) -> Result<Option<Vec<u8>>> {
+        let download_url = self.download_url(url);
+        if !matches!(download_url.scheme(), "http" | "https") {
+            return Ok(None);
+        }
+        let download_error = |source| Error::Download {
+            url: download_url.to_string(),
+            source: Box::new(source),
+        };
+        let mut response = self
+            .agent
+            .get(download_url.as_str())
+            .call()
+            .map_err(download_error)?;
+        match response.status().as_u16() {
+            404 | 410 => return Ok(None),
+            status if status >= 400 => {
+                return Err(download_error(ureq::Error::StatusCode(status)));
+            }
+            _ => {}
+        }
+        let content = response.body_mut().read_to_vec().map_err(download_error)?;
+        Ok(Some(content))
+    }
+}
+
+/// Download the schemas with the given ids, and those they reference transitively,
+/// into `out_dir`.
+///
+/// The layout of `out_dir` and the returned catalog are the same as for
+/// [`crate::vendor::vendor`]. References that can't be downloaded are returned in
+/// [`Vendored::unresolved`].
+pub fn fetch<'a>(
+    client: &Client,
+    ids: impl IntoIterator<Item = &'a str>,
+    out_dir: &Path,
+    name: String,
+) -> Result<Vendored> {
+    copy_closure(
+        |url| {
+            Ok(client.get(url)?.map(|content| Document {
+                content,
+                // For the format, which is best guessed from the id
+                path: PathBuf::from(url.path()),
+            }))
+        },
+        ids,
+        out_dir,
+        name,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::vendor::Unresolved;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve the files in `dir` over HTTP on a local port, until the test ends.
+    fn serve(dir: &'static str) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+                let path = request_line.split(' ').nth(1).unwrap_or_default();
+                let file = Path::new(dir).join(path.trim_start_matches('/'));
+                let response = match std::fs::read(&file) {
+                    Ok(content) if !path.contains("..") => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            content.len()
+                        )
+                        .into_bytes();
+                        response.extend(content);
+                        response
+                    }
+                    _ => {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                stream.write_all(&response).unwrap();
+            }
+        });
+        Url::parse(&format!("http://{}/", address)).unwrap()
+    }
+
+    #[test]
+    fn download_urls() {
+        let mut client = Client::new(None).unwrap();
+        client.add_mirror(
+            "https://example.com/".to_string(),
+            Url::parse("http://mirror.test/all/").unwrap(),
+        );
+        client.add_mirror(
+            "https://example.com/a/".to_string(),
+            Url::parse("http://mirror.test/a/").unwrap(),
+        );
+        let url = |s| Url::parse(s).unwrap();
+        assert_eq!(
+            client.download_url(&url("https://example.com/a/b.json")),
+            url("http://mirror.test/a/b.json")
+        );
+        assert_eq!(
+            client.download_url(&url("https://example.com/c.json")),
+            url("http://mirror.test/all/c.json")
+        );
+        assert_eq!(
+            client.download_url(&url("https://example.org/c.json")),
+            url("https://example.org/c.json")
+        );
+    }
+
+    #[test]
+    fn fetch_example() {
+        let mut client = Client::new(None).unwrap();
+        client.add_mirror(
+            "https://schema.example.com/fetch/".to_string(),
+            serve("test/fetch/schemas"),
+        );
+        // A port that nothing listens on
+        let offline = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        client.add_mirror(
+            "https://offline.example.com/".to_string(),
+            Url::parse(&format!("http://{}/", offline)).unwrap(),
+        );
+        let out_dir = TempDir::new("fetch");
+        let fetched = fetch(
+            &client,
+            ["https://schema.example.com/fetch/root.json"],
+            &out_dir,
+            "Fetched".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fetched.unresolved[0],
+            Unresolved {
+                reference: "https://schema.example.com/fetch/missing.json".to_string(),
+                from: "https://schema.example.com/fetch/root.json".to_string(),
+                reason: None,
+            }
+        );
+        // Failed downloads of references are reported, rather than failing the fetch
+        assert_eq!(fetched.unresolved.len(), 2);
+        assert_eq!(
+            fetched.unresolved[1].reference,
+            "https://offline.example.com/schema.json"
+        );
+        assert!(fetched.unresolved[1]
+            .reason
+            .as_ref()
+            .is_some_and(|reason| reason.starts_with("Could not download")));
+        assert_eq!(
+            fetched.catalog.groups[0]
+                .schemas
+                .iter()
+                .map(|s| (s.id.as_str(), s.location.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "https://schema.example.com/fetch/child.json",
+                    "schema.example.com/fetch/child.json"
+                ),
+                (
+                    "https://schema.example.com/fetch/root.json",
+                    "schema.example.com/fetch/root.json"
+                ),
+            ]
+        );
+        let catalog_file = out_dir.write(
+            "catalog.json",
+            &serde_json::to_string(&fetched.catalog).unwrap(),
+        );
+        fetched
+            .catalog
+            .check(&Default::default(), &catalog_file)
+            .unwrap();
+
+        assert!(matches!(
+            fetch(
+                &client,
+                ["https://schema.example.com/fetch/missing.json"],
+                &out_dir,
+                "Fetched".to_string(),
+            ),
+            Err(Error::SchemaNotFound { .. })
+        ));
+        assert!(matches!(
+            fetch(
+                &client,
+                ["https://offline.example.com/schema.json"],
+                &out_dir,
+                "Fetched".to_string(),
+            ),
+            Err(Error::Download { .. })
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+//! Document formats that schema and catalog files may be written in.
+//!
+//! Documents are always handled as `serde_json::Value`s; this module only deals
+//! with getting them in and out of their on-disk representation.
+
+use crate::error::{Error, FormatError, Result};
+use std::path::Path;
+
+/// A serialization format for schema and catalog documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// Plain JSON, as accepted by `serde_json`.
+    Json,
+    /// JSON with comments and trailing commas, as used by editor configurations.
+    Jsonc,
+    /// YAML, as parsed by `serde_yaml_ng`, which follows the YAML 1.1 rules.
+    Yaml,
+    /// TOML. This can not represent `null`, so it is mostly useful for catalogs.
+    Toml,
+}
+
+impl DocumentFormat {
+    /// Determine the format from the file extension, if it is a known one.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(DocumentFormat::Json),
+            "jsonc" => Some(DocumentFormat::Jsonc),
+            "yaml" | "yml" => Some(DocumentFormat::Yaml),
+            "toml" => Some(DocumentFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Parse a document in this format.
+    pub fn parse(self, content: &str) -> Result<serde_json::Value, FormatError> {
+        match self {
+            DocumentFormat::Json => Ok(serde_json::from_str(content)?),
+            DocumentFormat::Jsonc => {
+                let value = jsonc_parser::parse_to_serde_value::<Option<serde_json::Value>>(
+                    content,
+                    &Default::default(),
+                )?;
+                value.ok_or_else(|| "Document is empty".into())
+            }
+            DocumentFormat::Yaml => Ok(serde_yaml_ng::from_str(content)?),
+            DocumentFormat::Toml => Ok(toml::from_str(content)?),
+        }
+    }
+
+    /// Serialize a document in this format.
+    ///
+    /// Comments are not retained when parsing, so JSONC is written as plain JSON,
+    /// which is valid JSONC.
+    pub fn to_string_pretty<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        let result: Result<String, FormatError> = match self {
+            DocumentFormat::Json | DocumentFormat::Jsonc => {
+                serde_json::to_string_pretty(value).map_err(Into::into)
+            }
+            DocumentFormat::Yaml => serde_yaml_ng::to_string(value).map_err(Into::into),
+            DocumentFormat::Toml => toml::to_string_pretty(value).map_err(Into::into),
+        };
+        result.map_err(|source| Error::Serialize {
+            format: self,
+            source,
+        })
+    }
+}
+
+impl std::fmt::Display for DocumentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DocumentFormat::Json => "JSON",
+            DocumentFormat::Jsonc => "JSONC",
+            DocumentFormat::Yaml => "YAML",
+            DocumentFormat::Toml => "TOML",
+        })
+    }
+}
+
+impl std::str::FromStr for DocumentFormat {
+    type Err = Error;
+
+    /// Parse a format name, as written in a file extension.
+    fn from_str(s: &str) -> Result<Self> {
+        DocumentFormat::from_extension(Path::new(&format!("_.{}", s))).ok_or_else(|| {
+            Error::UnknownFormat {
+                name: s.to_string(),
+            }
+        })
+    }
+}
+
+/// Parse a document, using the file extension to determine its format.
+///
+/// If the extension is not recognized, the content is tried as JSON, JSONC and
+/// YAML, in that order. YAML is tried last because nearly any text is valid YAML.
+/// TOML is only used when the extension says so.
+pub fn parse_document(path: &Path, content: &str) -> Result<(DocumentFormat, serde_json::Value)> {
+    if let Some(format) = DocumentFormat::from_extension(path) {
+        let value = format.parse(content).map_err(|source| Error::Parse {
+            path: path.to_path_buf(),
+            format,
+            source,
+        })?;
+        return Ok((format, value));
+    }
+    for format in [DocumentFormat::Json, DocumentFormat::Jsonc] {
+        if let Ok(value) = format.parse(content) {
+            return Ok((format, value));
+        }
+    }
+    let value =
+        DocumentFormat::Yaml
+            .parse(content)
+            .map_err(|source| Error::UnrecognizedContent {
+                path: path.to_path_buf(),
+                source,
+            })?;
+    Ok((DocumentFormat::Yaml, value))
+}
+
+/// Read and parse a document. See [`parse_document`].
+pub fn read_document(path: &Path) -> Result<(DocumentFormat, serde_json::Value)> {
+    let content = std::fs::read_to_string(path).map_err(|source| Error::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_document(path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn by_extension() {
+        let (format, value) = parse_document(
+            Path::new("a.YML"),
+            "$id: https://example.com/a.json\ntitle: A\n",
+        )
+        .unwrap();
+        assert_eq!(format, DocumentFormat::Yaml);
+        assert_eq!(
+            value,
+            json!({"$id": "https://example.com/a.json", "title": "A"})
+        );
+
+        let (format, value) = parse_document(
+            Path::new("a.jsonc"),
+            "{\n  // the id\n  \"$id\": \"https://example.com/a.json\",\n}\n",
+        )
+        .unwrap();
+        assert_eq!(format, DocumentFormat::Jsonc);
+        assert_eq!(value, json!({"$id": "https://example.com/a.json"}));
+
+        assert!(parse_document(Path::new("a.json"), "{ /* no */ }").is_err());
+    }
+
+    #[test]
+    fn by_content() {
+        let path = Path::new("schema");
+        assert_eq!(
+            parse_document(path, "{\"a\": 1}").unwrap().0,
+            DocumentFormat::Json
+        );
+        assert_eq!(
+            parse_document(path, "{\"a\": 1, // one\n}").unwrap().0,
+            DocumentFormat::Jsonc
+        );
+        assert_eq!(
+            parse_document(path, "a: 1\n").unwrap().0,
+            DocumentFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(
+            "yml".parse::<DocumentFormat>().unwrap(),
+            DocumentFormat::Yaml
+        );
+        assert_eq!(
+            "TOML".parse::<DocumentFormat>().unwrap(),
+            DocumentFormat::Toml
+        );
+        assert!("xml".parse::<DocumentFormat>().is_err());
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let value = json!({"$ref": "file:///a.json#/definitions/b", "type": "object"});
+        let text = DocumentFormat::Yaml.to_string_pretty(&value).unwrap();
+        assert_eq!(DocumentFormat::Yaml.parse(&text).unwrap(), value);
+    }
+}
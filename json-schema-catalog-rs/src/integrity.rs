@@ -0,0 +1,102 @@
+//! [Subresource Integrity](https://www.w3.org/TR/SRI/) hashes of schema files.
+//!
+//! An integrity value is a space-separated list of `<algorithm>-<base64 digest>`
+//! tokens. Like browsers, we only look at the strongest algorithm that occurs, and
+//! accept the content if it matches any of the digests for that algorithm.
+
+use crate::error::{Error, Result};
+use base64::Engine as _;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::path::Path;
+
+/// Supported algorithms, from weakest to strongest.
+const ALGORITHMS: [&str; 3] = ["sha256", "sha384", "sha512"];
+
+fn digest(algorithm: &str, content: &[u8]) -> String {
+    let digest = match algorithm {
+        "sha256" => Sha256::digest(content).to_vec(),
+        "sha384" => Sha384::digest(content).to_vec(),
+        "sha512" => Sha512::digest(content).to_vec(),
+        _ => unreachable!("unsupported algorithm {}", algorithm),
+    };
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// Compute the `sha256-...` integrity value of some content.
+pub fn compute(content: &[u8]) -> String {
+    format!("sha256-{}", digest("sha256", content))
+}
+
+/// Check that the content of a file matches an integrity value.
+///
+/// `path` is only used in errors.
+pub fn verify(integrity: &str, content: &[u8], path: &Path) -> Result<()> {
+    let tokens = integrity
+        .split_ascii_whitespace()
+        .filter_map(|token| {
+            // Options such as "?ct=application/json" are allowed after the digest
+            let token = token.split('?').next().unwrap_or_default();
+            let (algorithm, digest) = token.split_once('-')?;
+            let strength = ALGORITHMS.iter().position(|a| *a == algorithm)?;
+            Some((strength, algorithm, digest))
+        })
+        .collect::<Vec<_>>();
+    let Some(strongest) = tokens.iter().map(|(strength, _, _)| *strength).max() else {
+        return Err(Error::UnsupportedIntegrity {
+            integrity: integrity.to_string(),
+        });
+    };
+    let algorithm = ALGORITHMS[strongest];
+    let actual = digest(algorithm, content);
+    if tokens
+        .iter()
+        .any(|(strength, _, expected)| *strength == strongest && *expected == actual)
+    {
+        Ok(())
+    } else {
+        Err(Error::IntegrityMismatch {
+            path: path.to_path_buf(),
+            expected: integrity.to_string(),
+            actual: format!("{}-{}", algorithm, actual),
+        })
+    }
+}
+
+/// Read a file and check it against an integrity value.
+pub fn verify_file(integrity: &str, path: &Path) -> Result<()> {
+    let content = std::fs::read(path).map_err(|source| Error::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    verify(integrity, &content, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_and_verify() {
+        let path = Path::new("a.json");
+        let integrity = compute(b"{}");
+        assert_eq!(
+            integrity,
+            "sha256-RBNvo1WzZ4oRRq0W9+hknpT7T8If536DEMBg9hyq/4o="
+        );
+        verify(&integrity, b"{}", path).unwrap();
+        assert!(matches!(
+            verify(&integrity, b"{ }", path),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+
+        // Only the strongest algorithm counts
+        let sha512 = format!("sha512-{}", digest("sha512", b"{ }"));
+        verify(&format!("{} {}", integrity, sha512), b"{ }", path).unwrap();
+        assert!(verify(&format!("{} {}", integrity, sha512), b"{}", path).is_err());
+
+        assert!(matches!(
+            verify("md5-AAAA", b"{}", path),
+            Err(Error::UnsupportedIntegrity { .. })
+        ));
+    }
+}
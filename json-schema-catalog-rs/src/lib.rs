@@ -1,14 +1,34 @@
-use anyhow::{bail, Context as _, Ok, Result};
 use schemars::schema::RootSchema;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+
+pub mod cache;
+pub mod config;
+pub mod edit;
+pub mod error;
+pub mod export;
+pub mod fetch;
+pub mod format;
+pub mod integrity;
+pub mod references;
+pub mod schemastore;
+#[cfg(test)]
+mod test_util;
+mod uri;
+pub mod vendor;
+pub mod xml_catalog;
+
+pub use error::{Error, Result};
 
 pub struct CheckOptions {
     pub require_matching_id: bool,
+    /// Files that the policy refuses are reported as errors.
+    pub path_policy: PathPolicy,
 }
 impl CheckOptions {
     pub fn new() -> Self {
         CheckOptions {
             require_matching_id: true,
+            path_policy: PathPolicy::default(),
         }
     }
 }
@@ -18,195 +38,805 @@ impl Default for CheckOptions {
     }
 }
 
-include!(concat!(
-    env!("OUT_DIR"),
-    "/generated/schema_catalog_schema.rs"
-));
+// Only the catalog types are used; typify also emits an unused conversion error type.
+#[allow(dead_code)]
+mod generated {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/generated/schema_catalog_schema.rs"
+    ));
+}
+pub use generated::{Catalog, CatalogGroup, Schema};
 
 // struct declared in generated file
 impl Catalog {
-    pub fn check(&self, opts: &CheckOptions, file_name: &str) -> Result<()> {
-        let base_dir = std::path::Path::new(file_name).parent().unwrap();
+    /// Read a catalog file in any of the supported [`format::DocumentFormat`]s.
+    pub fn read(file: &Path) -> Result<Catalog> {
+        let (_format, value) = format::read_document(file)?;
+        serde_json::from_value(value).map_err(|source| Error::InvalidCatalog {
+            path: file.to_path_buf(),
+            source,
+        })
+    }
+    /// Check the catalog and the catalogs it includes.
+    pub fn check(&self, opts: &CheckOptions, file_name: &Path) -> Result<()> {
+        let mut stack = vec![canonical_catalog_path(file_name)?];
+        self.check_with_includes(opts, file_name, &mut stack)
+    }
+    fn check_with_includes(
+        &self,
+        opts: &CheckOptions,
+        file_name: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let base_dir = file_name.parent().unwrap_or(Path::new(""));
+        let in_catalog = |e: Error| Error::InCatalog {
+            name: self.name.clone(),
+            file: file_name.into(),
+            source: Box::new(e),
+        };
         for group in &self.groups {
-            group
-                .check(opts, base_dir)
-                .with_context(|| format!("in catalog {}, file {}", self.name, file_name))?;
+            group.check(opts, base_dir).map_err(in_catalog)?;
+        }
+        for include in &self.include {
+            let file = base_dir.join(include);
+            let canonical = canonical_catalog_path(&file).map_err(in_catalog)?;
+            check_cycle(stack, &canonical).map_err(in_catalog)?;
+            let catalog = Catalog::read(&file).map_err(in_catalog)?;
+            stack.push(canonical);
+            catalog.check_with_includes(opts, &file, stack)?;
+            stack.pop();
         }
         Ok(())
     }
-    pub fn index(&self, basedir: &str, index: &mut Index) {
+    /// Add the schemas to the index. `basedir` is the directory of the catalog file.
+    pub fn index(&self, basedir: &Path, index: &mut Index) {
         for group in &self.groups {
             group.index(basedir, index);
         }
     }
 }
+fn canonical_catalog_path(file: &Path) -> Result<PathBuf> {
+    std::fs::canonicalize(file).map_err(|source| Error::Access {
+        path: file.into(),
+        source,
+    })
+}
+
+fn check_cycle(stack: &[PathBuf], file: &Path) -> Result<()> {
+    if let Some(start) = stack.iter().position(|f| f == file) {
+        let cycle = stack[start..]
+            .iter()
+            .cloned()
+            .chain(std::iter::once(file.to_path_buf()))
+            .collect();
+        return Err(Error::IncludeCycle { cycle });
+    }
+    Ok(())
+}
+
+/// A catalog and the file it was read from.
+pub struct CatalogFile {
+    pub file: PathBuf,
+    pub catalog: Catalog,
+}
+impl CatalogFile {
+    /// The directory of the catalog file, which its locations are relative to.
+    pub fn dir(&self) -> &Path {
+        self.file.parent().unwrap_or(Path::new(""))
+    }
+}
+
+/// Read catalog files and the catalogs they include, recursively.
+///
+/// Included catalogs come before the catalog that includes them, so that indexing
+/// the result in order lets the including catalog take precedence. A catalog that
+/// is reached more than once is only returned the first time. Include cycles are
+/// an error.
+pub fn read_catalogs(
+    files: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Vec<CatalogFile>> {
+    let mut seen = std::collections::HashSet::new();
+    let mut result = vec![];
+    for file in files {
+        read_catalog_with_includes(file.as_ref(), &mut vec![], &mut seen, &mut result)?;
+    }
+    Ok(result)
+}
+
+fn read_catalog_with_includes(
+    file: &Path,
+    stack: &mut Vec<PathBuf>,
+    seen: &mut std::collections::HashSet<PathBuf>,
+    result: &mut Vec<CatalogFile>,
+) -> Result<()> {
+    let canonical = canonical_catalog_path(file)?;
+    check_cycle(stack, &canonical)?;
+    if !seen.insert(canonical.clone()) {
+        return Ok(());
+    }
+    let catalog = Catalog::read(file)?;
+    let base_dir = file.parent().unwrap_or(Path::new(""));
+    stack.push(canonical);
+    for include in &catalog.include {
+        let included = base_dir.join(include);
+        read_catalog_with_includes(&included, stack, seen, result).map_err(|e| {
+            Error::IncludedFrom {
+                name: catalog.name.clone(),
+                file: file.into(),
+                source: Box::new(e),
+            }
+        })?;
+    }
+    stack.pop();
+    result.push(CatalogFile {
+        file: file.to_path_buf(),
+        catalog,
+    });
+    Ok(())
+}
+
 // struct declared in generated file
 impl CatalogGroup {
     pub fn check(&self, opts: &CheckOptions, base_dir: &Path) -> Result<()> {
         if self.name.is_empty() {
-            bail!("Group name is empty");
+            return Err(Error::EmptyGroupName);
         }
         if self.base_location.is_empty() {
-            bail!("Group base location is empty".to_string());
+            return Err(Error::EmptyBaseLocation);
         }
-        let base_dir = base_dir.join(&self.base_location);
+        let in_group = |e: Error| Error::InGroup {
+            name: self.name.clone(),
+            source: Box::new(e),
+        };
+        let root = base_dir;
+        let base_dir = root.join(location_path(&self.base_location).map_err(in_group)?);
         for schema in &self.schemas {
-            schema
-                .check(opts, &base_dir)
-                .with_context(|| format!("in catalog group {}", self.name))?;
+            let location =
+                resolve_location(&self.base_location, &schema.location).map_err(in_group)?;
+            opts.path_policy.check(root, &location).map_err(in_group)?;
+            schema.check(opts, &base_dir).map_err(in_group)?;
+        }
+        if let Some(prefix) = &self.uri_prefix {
+            self.check_prefix(opts, prefix, root).map_err(in_group)?;
         }
         Ok(())
     }
-    pub fn index<'a>(&self, basedir: &'a str, index: &mut Index) {
+    /// Check every file under the directory of a `uriPrefix` group, as if it were
+    /// listed with the id that the prefix maps to it.
+    fn check_prefix(&self, opts: &CheckOptions, prefix: &str, root: &Path) -> Result<()> {
+        if prefix.is_empty() {
+            return Err(Error::EmptyUriPrefix);
+        }
+        let base_location = location_path(&self.base_location)?;
+        let base_dir = &root.join(&base_location);
+        let files = files_under(base_dir).map_err(|source| Error::ListFiles {
+            dir: base_dir.to_path_buf(),
+            prefix: prefix.to_string(),
+            source,
+        })?;
+        for file in files {
+            let location = file.to_string_lossy();
+            let id = format!(
+                "{}{}",
+                prefix,
+                uri::path_to_uri(&location.replace(std::path::MAIN_SEPARATOR, "/"))
+            );
+            opts.path_policy.check(root, &base_location.join(&file))?;
+            check_schema_file(opts, &id, None, &base_dir.join(&file))?;
+        }
+        Ok(())
+    }
+    /// Add the schemas to the index. `basedir` is the directory of the catalog file.
+    pub fn index(&self, basedir: &Path, index: &mut Index) {
         for schema in &self.schemas {
             schema.index(basedir, &self.base_location, index);
         }
+        if let Some(prefix) = &self.uri_prefix {
+            index.prefixes.push(PrefixEntry {
+                prefix: uri::normalize_id(prefix),
+                root: basedir.to_path_buf(),
+                base_location: self.base_location.clone(),
+            });
+        }
+    }
+}
+
+/// List the files in a directory recursively, relative to that directory, sorted.
+fn files_under(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative) = dirs.pop() {
+        for entry in std::fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
     }
+    files.sort();
+    Ok(files)
 }
 // struct declared in generated file
 impl Schema {
     pub fn check(&self, opts: &CheckOptions, base_dir: &Path) -> Result<()> {
         if self.id.is_empty() {
-            bail!("Schema id is empty".to_string());
+            return Err(Error::EmptySchemaId);
         }
         if self.location.is_empty() {
-            bail!("Schema location is empty".to_string());
-        }
-        let location = base_dir.join(&self.location);
-        let location = location.as_path();
-        if let Err(e) = std::fs::metadata(&location) {
-            bail!(
-                "Could not access {} as file: {}",
-                location.to_string_lossy(),
-                e
-            );
+            return Err(Error::EmptySchemaLocation);
         }
-        let content = match std::fs::read_to_string(&location) {
-            Err(e) => {
-                bail!(
-                    "Could not read {} as file: {}",
-                    location.to_string_lossy(),
-                    e
-                );
-            }
-            Result::Ok(content) => content,
-        };
-        let value = match serde_json::from_str::<serde_json::Value>(&content) {
-            Result::Ok(value) => value,
-            Result::Err(e) => {
-                bail!(
-                    "Could not parse {} as JSON: {}",
-                    &location.to_string_lossy(),
-                    e
-                );
-            }
-        };
-
-        // If an id is present, it must match the recorded schema id
-        if opts.require_matching_id {
-            if let Some(id) = value.get("id") {
-                if id != &self.id {
-                    bail!(
-                        "Recorded schema id {} does not match id {} in file {}",
-                        self.id,
-                        id,
-                        location.to_string_lossy()
-                    );
-                }
-            }
-        }
-
-        // Idea: validate the whole schema, optionally
-
-        Ok(())
+        check_schema_file(
+            opts,
+            &self.id,
+            self.integrity.as_deref(),
+            &base_dir.join(location_path(&self.location)?),
+        )
     }
 
-    fn index(&self, basedir: &str, base_location: &str, index: &mut Index) {
+    /// Add the schema to the index. `root` is the directory of the catalog file.
+    fn index(&self, root: &Path, base_location: &str, index: &mut Index) {
         index.by_id.insert(
-            self.id.clone(),
+            uri::normalize_id(&self.id),
             IndexEntry {
-                basedir: basedir.to_string(),
+                id: self.id.clone(),
+                root: root.to_path_buf(),
                 base_location: base_location.to_string(),
-                file: self.location.clone(),
+                location: self.location.clone(),
+                integrity: self.integrity.clone(),
             },
         );
     }
 }
 
+fn check_schema_file(
+    opts: &CheckOptions,
+    id: &str,
+    integrity: Option<&str>,
+    location: &Path,
+) -> Result<()> {
+    if let Err(source) = std::fs::metadata(location) {
+        return Err(Error::Access {
+            path: location.to_path_buf(),
+            source,
+        });
+    }
+    if let Some(integrity) = integrity {
+        integrity::verify_file(integrity, location)?;
+    }
+    let (_format, value) = format::read_document(location)?;
+
+    // If an id is present, it must match the recorded schema id
+    if opts.require_matching_id {
+        if let Some(actual) = value.get("id") {
+            if actual != id {
+                return Err(Error::IdMismatch {
+                    path: location.to_path_buf(),
+                    expected: id.to_string(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+    }
+
+    // Idea: validate the whole schema, optionally
+
+    Ok(())
+}
+
+/// A `uriPrefix` group in the index.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct PrefixEntry {
+    prefix: String,
+    root: PathBuf,
+    base_location: String,
+}
+impl PrefixEntry {
+    /// Map an id under the prefix to an entry for the corresponding file.
+    fn resolve(&self, id: &str) -> Option<IndexEntry> {
+        let rest = id.strip_prefix(&self.prefix)?;
+        let rest = rest.split(['#', '?']).next().unwrap_or_default();
+        let file = uri::uri_to_path(rest).ok()?;
+        if file.as_os_str().is_empty()
+            || !file.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            return None;
+        }
+        Some(IndexEntry {
+            id: id.to_string(),
+            root: self.root.clone(),
+            base_location: self.base_location.clone(),
+            location: rest.to_string(),
+            integrity: None,
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct IndexEntry {
-    basedir: String,
+    /// The id as written in the catalog.
+    id: String,
+    /// The directory of the catalog file.
+    root: PathBuf,
     base_location: String,
-    file: String,
+    location: String,
+    integrity: Option<String>,
 }
 impl IndexEntry {
-    fn get_path(&self) -> String {
-        let mut path = std::path::PathBuf::new();
-        path.push(&self.basedir);
-        path.push(&self.base_location);
-        path.push(&self.file);
-        return path.to_string_lossy().to_string();
+    /// The location relative to `root`, or an absolute path.
+    fn location(&self) -> Result<PathBuf> {
+        resolve_location(&self.base_location, &self.location)
+    }
+    fn get_path(&self) -> Result<PathBuf> {
+        Ok(normalize_path(&self.root.join(self.location()?)))
     }
 }
 
+/// Convert a `baseLocation` or `location` to a path. These are URI references:
+/// relative references are percent-decoded, and `file:` URLs are converted to
+/// absolute paths. Windows paths with a drive letter are taken as they are. Other
+/// URI schemes are not supported.
+pub fn location_path(location: &str) -> Result<PathBuf> {
+    uri::uri_to_path(location).map_err(|reason| Error::InvalidLocation {
+        location: location.to_string(),
+        reason,
+    })
+}
+
+/// Resolve the `location` of a schema against the `baseLocation` of its group, as
+/// RFC 3986 does for the catalog file's URL, with the base location taken to be a
+/// directory. The result is relative to the directory of the catalog file, or absolute.
+pub fn resolve_location(base_location: &str, location: &str) -> Result<PathBuf> {
+    Ok(location_path(base_location)?.join(location_path(location)?))
+}
+
+/// Write a path as a `baseLocation` or `location`, which is the inverse of
+/// [`location_path`]. Absolute paths become `file:` URLs.
+pub fn path_to_location(path: &Path) -> Result<String> {
+    if path.is_absolute() {
+        return url::Url::from_file_path(path)
+            .map(|url| url.to_string())
+            .map_err(|()| Error::InvalidPath {
+                path: path.to_path_buf(),
+                part: "file URL",
+            });
+    }
+    let location = path.to_str().ok_or_else(|| Error::NotUtf8 {
+        path: path.to_path_buf(),
+    })?;
+    Ok(uri::path_to_uri(
+        &location.replace(std::path::MAIN_SEPARATOR, "/"),
+    ))
+}
+
+/// Remove `.` components and resolve `..` components against the preceding
+/// component, without accessing the file system.
+///
+/// `..` components that would go above the start of a relative path are kept, and
+/// those at the root of an absolute path are dropped. Note that this differs from
+/// what the file system does when a directory is a symlink; see
+/// [`Index::set_canonicalize`] for resolving those.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                Some(Component::ParentDir) | Some(Component::CurDir) | None => {
+                    result.push(component)
+                }
+            },
+            _ => result.push(component),
+        }
+    }
+    if result.as_os_str().is_empty() {
+        result.push(Component::CurDir);
+    }
+    result
+}
+
 /// An index for looking up schema files by their id.
 ///
 /// The index is filled by calling the `index` method on a `Catalog`, `CatalogGroup` or `Schema`.
+///
+/// Ids that are listed explicitly take precedence over `uriPrefix` groups. Among the
+/// prefix groups, the longest matching prefix wins, and an id only resolves if the
+/// file it maps to exists.
+///
+/// Paths are relative to the current directory if the catalog paths were, and are
+/// normalized with [`normalize_path`].
 pub struct Index {
     by_id: std::collections::HashMap<String, IndexEntry>,
+    prefixes: Vec<PrefixEntry>,
+    canonicalize: bool,
+    integrity_policy: IntegrityPolicy,
+    path_policy: PathPolicy,
 }
+
+/// How the [`Index`] treats the `integrity` of schemas when resolving them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntegrityPolicy {
+    /// Don't read the files.
+    #[default]
+    Ignore,
+    /// Refuse files that don't match their recorded integrity.
+    Verify,
+    /// Like `Verify`, and also refuse schemas without a recorded integrity, which
+    /// includes all `uriPrefix` groups. This makes the catalogs work like a lockfile.
+    Require,
+}
+impl std::str::FromStr for IntegrityPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "ignore" => Ok(IntegrityPolicy::Ignore),
+            "verify" => Ok(IntegrityPolicy::Verify),
+            "require" => Ok(IntegrityPolicy::Require),
+            _ => Err(format!(
+                "Unknown integrity policy {}, expected one of ignore, verify, require",
+                s
+            )),
+        }
+    }
+}
+
+/// Restrictions on the files that catalogs can point to, for catalogs that are not
+/// trusted, such as those that come with third-party packages.
+///
+/// Locations are checked against the root of their catalog, which is the directory
+/// of the catalog file. By default, nothing is restricted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathPolicy {
+    /// Refuse absolute locations, locations that `..` takes outside of the root,
+    /// and files that symlinks make end up outside of the root.
+    pub confine: bool,
+    /// Refuse files that are larger than this many bytes.
+    pub max_file_size: Option<u64>,
+}
+impl PathPolicy {
+    /// Check the location of a file, which is the `baseLocation` and `location` of
+    /// a schema joined, relative to `root`.
+    pub fn check(&self, root: &Path, location: &Path) -> Result<()> {
+        let path = normalize_path(&root.join(location));
+        if self.confine {
+            if location.has_root()
+                || matches!(location.components().next(), Some(Component::Prefix(_)))
+            {
+                return Err(Error::AbsoluteLocation { path });
+            }
+            if let Some(Component::ParentDir) = normalize_path(location).components().next() {
+                return Err(Error::OutsideRoot {
+                    path,
+                    root: root.to_path_buf(),
+                });
+            }
+            // Files that don't exist can't be read either, so they are left to the caller
+            if let (Ok(canonical_root), Ok(target)) =
+                (std::fs::canonicalize(root), std::fs::canonicalize(&path))
+            {
+                if !target.starts_with(&canonical_root) {
+                    return Err(Error::SymlinkOutsideRoot {
+                        path,
+                        target,
+                        root: root.to_path_buf(),
+                    });
+                }
+            }
+        }
+        if let Some(max) = self.max_file_size {
+            if let Ok(metadata) = std::fs::metadata(&path) {
+                if metadata.len() > max {
+                    return Err(Error::FileTooLarge {
+                        path,
+                        size: metadata.len(),
+                        max,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Index {
     pub fn new() -> Self {
         Index {
             by_id: std::collections::HashMap::new(),
+            prefixes: Vec::new(),
+            canonicalize: false,
+            integrity_policy: IntegrityPolicy::Ignore,
+            path_policy: PathPolicy::default(),
+        }
+    }
+    pub fn set_integrity_policy(&mut self, policy: IntegrityPolicy) {
+        self.integrity_policy = policy;
+    }
+    pub fn set_path_policy(&mut self, policy: PathPolicy) {
+        self.path_policy = policy;
+    }
+    /// Return canonical, absolute paths with all symlinks resolved, instead of
+    /// normalized ones. Paths that can not be canonicalized, for instance because
+    /// the file doesn't exist, are still returned normalized.
+    pub fn set_canonicalize(&mut self, canonicalize: bool) {
+        self.canonicalize = canonicalize;
+    }
+    /// Look up an id that has been normalized with [`uri::normalize_id`].
+    fn get_entry(&self, id: &str) -> Option<IndexEntry> {
+        if let Some(entry) = self.by_id.get(id) {
+            return Some(entry.clone());
+        }
+        self.prefixes
+            .iter()
+            .filter(|p| id.starts_with(&p.prefix))
+            .max_by_key(|p| p.prefix.len())
+            .and_then(|p| p.resolve(id))
+            .filter(|entry| entry.get_path().is_ok_and(|path| path.is_file()))
+    }
+    /// Find the file of a schema by its id, which is normalized like the ids in
+    /// the catalogs. See [`Index::resolve`].
+    pub fn get_path(&self, id: &str) -> Option<PathBuf> {
+        self.try_get_path(id).ok().flatten()
+    }
+    /// Find the file of the schema that a URL refers to.
+    ///
+    /// Both the URL and the ids in the catalogs are compared in a normalized form:
+    /// scheme and host are case-insensitive, default ports are omitted, dot-segments
+    /// are resolved, percent-encoding is normalized, and the fragment is ignored.
+    ///
+    /// Files that are refused by the [`IntegrityPolicy`] or the [`PathPolicy`] are
+    /// not returned; use
+    /// [`Index::try_resolve`] to find out why.
+    pub fn resolve(&self, url: &url::Url) -> Option<PathBuf> {
+        self.try_resolve(url).ok().flatten()
+    }
+    /// Like [`Index::get_path`], but returns an error if the [`IntegrityPolicy`]
+    /// or the [`PathPolicy`] refuses the file.
+    pub fn try_get_path(&self, id: &str) -> Result<Option<PathBuf>> {
+        self.get_normalized(&uri::normalize_id(id))
+    }
+    /// Like [`Index::resolve`], but returns an error if the [`IntegrityPolicy`]
+    /// or the [`PathPolicy`] refuses the file.
+    pub fn try_resolve(&self, url: &url::Url) -> Result<Option<PathBuf>> {
+        self.get_normalized(&uri::normalize_url(url))
+    }
+    fn get_normalized(&self, id: &str) -> Result<Option<PathBuf>> {
+        let Some(entry) = self.get_entry(id) else {
+            return Ok(None);
+        };
+        let path = entry.get_path()?;
+        self.path_policy.check(&entry.root, &entry.location()?)?;
+        match (&entry.integrity, self.integrity_policy) {
+            (_, IntegrityPolicy::Ignore) | (None, IntegrityPolicy::Verify) => {}
+            (Some(integrity), _) => integrity::verify_file(integrity, &path)?,
+            (None, IntegrityPolicy::Require) => {
+                return Err(Error::MissingIntegrity { id: id.to_string() })
+            }
+        }
+        if self.canonicalize {
+            if let Ok(canonical) = std::fs::canonicalize(&path) {
+                return Ok(Some(canonical));
+            }
+        }
+        Ok(Some(path))
+    }
+}
+impl Default for Index {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Finds the ids of schema files, which is the reverse of [`Index::get_path`].
+///
+/// Files are compared by their absolute, normalized path, and by their canonical
+/// path, so that symlinks and relative paths lead to the same id.
+pub struct ReverseIndex {
+    by_path: std::collections::HashMap<PathBuf, String>,
+    /// The directories of `uriPrefix` groups, with their prefix.
+    prefixes: Vec<(PathBuf, String)>,
+}
+
+/// The forms of a path that [`ReverseIndex`] compares.
+fn reverse_keys(path: &Path) -> Vec<PathBuf> {
+    let mut keys = vec![];
+    if let Ok(absolute) = std::path::absolute(path) {
+        keys.push(normalize_path(&absolute));
+    }
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        if !keys.contains(&canonical) {
+            keys.push(canonical);
         }
     }
-    fn get_entry(&self, id: &str) -> Option<&IndexEntry> {
-        self.by_id.get(id)
+    keys
+}
+
+impl Index {
+    /// Build a [`ReverseIndex`] of the schemas in this index.
+    pub fn reverse(&self) -> ReverseIndex {
+        let mut by_path = std::collections::HashMap::new();
+        for entry in self.by_id.values() {
+            let Ok(path) = entry.get_path() else {
+                continue;
+            };
+            for key in reverse_keys(&path) {
+                by_path
+                    .entry(key)
+                    .and_modify(|id: &mut String| {
+                        if entry.id < *id {
+                            id.clone_from(&entry.id);
+                        }
+                    })
+                    .or_insert_with(|| entry.id.clone());
+            }
+        }
+        let mut prefixes = vec![];
+        for prefix in &self.prefixes {
+            let Ok(base_location) = location_path(&prefix.base_location) else {
+                continue;
+            };
+            for key in reverse_keys(&prefix.root.join(base_location)) {
+                prefixes.push((key, prefix.prefix.clone()));
+            }
+        }
+        ReverseIndex { by_path, prefixes }
     }
-    pub fn get_path(&self, id: &str) -> Option<String> {
-        let entry = self.get_entry(id)?;
-        Some(entry.get_path())
+}
+
+impl ReverseIndex {
+    /// The id of the schema in a file. If the catalogs list the file under more than
+    /// one id, the smallest one is returned. Explicitly listed ids take precedence
+    /// over `uriPrefix` groups, of which the one with the deepest directory wins.
+    pub fn get_id(&self, path: &Path) -> Option<String> {
+        let keys = reverse_keys(path);
+        if let Some(id) = keys.iter().find_map(|key| self.by_path.get(key)) {
+            return Some(id.clone());
+        }
+        keys.iter()
+            .flat_map(|key| {
+                self.prefixes.iter().filter_map(move |(dir, prefix)| {
+                    let rest = key.strip_prefix(dir).ok()?;
+                    Some((dir.components().count(), prefix, rest))
+                })
+            })
+            .filter(|(_, _, rest)| !rest.as_os_str().is_empty())
+            .max_by_key(|(depth, _, _)| *depth)
+            .map(|(_, prefix, rest)| {
+                let rest = rest.to_string_lossy();
+                format!(
+                    "{}{}",
+                    prefix,
+                    uri::path_to_uri(&rest.replace(std::path::MAIN_SEPARATOR, "/"))
+                )
+            })
     }
 }
 
+/// Get the id of a schema document: `$id`, or the draft-04 `id`.
+pub fn schema_id(schema: &serde_json::Value) -> Option<&str> {
+    schema
+        .get("$id")
+        .or_else(|| schema.get("id"))
+        .and_then(|id| id.as_str())
+}
+
 /// Generate a singleton group from a schema file.
-pub fn group_from_schema(file: &str, schema: &serde_json::Value) -> Result<CatalogGroup> {
-    let schema = serde_json::from_value::<RootSchema>(schema.clone())?;
+pub fn group_from_schema(path: &Path, schema: &serde_json::Value) -> Result<CatalogGroup> {
+    let schema = serde_json::from_value::<RootSchema>(schema.clone()).map_err(|source| {
+        Error::NotASchema {
+            path: path.to_path_buf(),
+            source,
+        }
+    })?;
     let id = schema
         .schema
         .metadata
         .as_ref()
         .and_then(|m| m.id.clone())
-        .ok_or_else(|| anyhow::format_err!("Schema {} does not have an $id field", file))?;
-    let base_dir = std::path::Path::new(file)
-        .parent()
-        .ok_or_else(|| anyhow::format_err!("Could not get parent directory of {}", file))?;
-    let file_name = std::path::Path::new(file)
-        .file_name()
-        .ok_or_else(|| anyhow::format_err!("Could not get file name from {}", file))?;
+        .ok_or_else(|| Error::MissingSchemaField {
+            path: path.to_path_buf(),
+            field: "$id",
+        })?;
+    let base_dir = path.parent().ok_or_else(|| Error::InvalidPath {
+        path: path.to_path_buf(),
+        part: "parent directory",
+    })?;
+    let file_name = path.file_name().ok_or_else(|| Error::InvalidPath {
+        path: path.to_path_buf(),
+        part: "file name",
+    })?;
     let name = schema
         .schema
         .metadata
         .as_ref()
         .and_then(|m| m.title.clone())
-        .ok_or_else(|| anyhow::format_err!("Schema {} does not have a title field", file))?
-        .clone();
+        .ok_or_else(|| Error::MissingSchemaField {
+            path: path.to_path_buf(),
+            field: "title",
+        })?;
     Ok(CatalogGroup {
         name,
-        base_location: base_dir.to_string_lossy().to_string(),
+        base_location: path_to_location(base_dir)?,
         schemas: vec![Schema {
             id,
-            location: file_name.to_string_lossy().to_string(),
+            integrity: None,
+            location: path_to_location(Path::new(file_name))?,
         }],
+        uri_prefix: None,
     })
 }
 
-fn group_key(group: &CatalogGroup) -> (String, String) {
-    (group.base_location.clone(), group.name.clone())
+/// Read a schema file and generate a singleton group from it, like
+/// [`group_from_schema`], recording the integrity of the file.
+pub fn group_from_schema_file(path: &Path) -> Result<CatalogGroup> {
+    let read_error = |source| Error::Read {
+        path: path.to_path_buf(),
+        source,
+    };
+    let content = std::fs::read(path).map_err(read_error)?;
+    let text = std::str::from_utf8(&content)
+        .map_err(|e| read_error(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+    let (_format, value) = format::parse_document(path, text)?;
+    let mut group = group_from_schema(path, &value)?;
+    group.schemas[0].integrity = Some(integrity::compute(&content));
+    Ok(group)
 }
 
-/// Merge groups into a single catalog. Groups with matching base_location and name
-/// are merged into a single group.
+/// Express `path` relative to the directory `base`.
+///
+/// Both are made absolute and normalized with [`normalize_path`] first, so the
+/// result only has `..` components at the start. If they have no common root, as
+/// with different drives on Windows, the absolute path is returned.
+pub fn relative_path(path: &Path, base: &Path) -> std::io::Result<PathBuf> {
+    let absolute = |p: &Path| {
+        let p = if p.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            p
+        };
+        std::path::absolute(p).map(|p| normalize_path(&p))
+    };
+    let path = absolute(path)?;
+    let base = absolute(base)?;
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return Ok(path);
+    }
+    let mut result = PathBuf::new();
+    for _ in base.components().skip(common) {
+        result.push(Component::ParentDir);
+    }
+    for component in path.components().skip(common) {
+        result.push(component);
+    }
+    if result.as_os_str().is_empty() {
+        result.push(Component::CurDir);
+    }
+    Ok(result)
+}
+
+fn group_key(group: &CatalogGroup) -> (String, String, Option<String>) {
+    (
+        group.base_location.clone(),
+        group.name.clone(),
+        group.uri_prefix.clone(),
+    )
+}
+
+/// Merge groups into a single catalog. Groups with matching base_location, name and
+/// uri_prefix are merged into a single group.
 pub fn catalog_from_groups(name: String, groups: Vec<CatalogGroup>) -> Result<Catalog> {
     let mut groups = groups.clone();
     groups.sort_by_key(group_key);
@@ -223,11 +853,16 @@ pub fn catalog_from_groups(name: String, groups: Vec<CatalogGroup>) -> Result<Ca
                 name: group.name,
                 base_location: group.base_location,
                 schemas,
+                uri_prefix: group.uri_prefix,
             })
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>>>()?;
 
-    Ok(Catalog { name, groups })
+    Ok(Catalog {
+        name,
+        groups,
+        include: vec![],
+    })
 }
 
 #[cfg(test)]
@@ -254,13 +889,16 @@ mod tests {
         });
         let catalog_expect = Catalog {
             name: "foo".to_string(),
+            include: vec![],
             groups: vec![CatalogGroup {
                 name: "I think we'll mostly ignore names".to_string(),
                 base_location: "vendor".to_string(),
                 schemas: vec![Schema {
                     id: "foo".to_string(),
+                    integrity: None,
                     location: "schema_catalog_schema.json".to_string(),
                 }],
+                uri_prefix: None,
             }],
         };
         // parse the catalog json with serde
@@ -273,7 +911,7 @@ mod tests {
         let catalog: Catalog =
             serde_json::from_str(&std::fs::read_to_string("./example.json").unwrap()).unwrap();
         catalog
-            .check(&Default::default(), "./example.json")
+            .check(&Default::default(), Path::new("./example.json"))
             .unwrap();
     }
 
@@ -282,26 +920,417 @@ mod tests {
         let catalog: Catalog =
             serde_json::from_str(&std::fs::read_to_string("test/example.json").unwrap()).unwrap();
         catalog
-            .check(&Default::default(), "test/example.json")
+            .check(&Default::default(), Path::new("test/example.json"))
             .unwrap();
     }
 
+    #[test]
+    fn alternative_formats() {
+        let expected = Catalog::read(Path::new("test/example.json")).unwrap();
+        for file in [
+            "test/example.yaml",
+            "test/example.toml",
+            "test/example.jsonc",
+        ] {
+            let catalog = Catalog::read(Path::new(file)).unwrap();
+            assert_eq!(catalog, expected, "{}", file);
+            catalog.check(&Default::default(), Path::new(file)).unwrap();
+        }
+    }
+
     #[test]
     fn test_lookup() {
         let catalog: Catalog =
             serde_json::from_str(&std::fs::read_to_string("test/example.json").unwrap()).unwrap();
         let mut index = Index::new();
-        catalog.index("test", &mut index);
+        catalog.index(Path::new("test"), &mut index);
+        assert_eq!(
+            index.get_path("https://schema.example.com/schema/schema_catalog_schema.json"),
+            Some(PathBuf::from("vendor/schema_catalog_schema.json"))
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        for (path, expected) in [
+            ("test/../vendor/a.json", "vendor/a.json"),
+            ("./a/./b/../c", "a/c"),
+            ("../../a/../b", "../../b"),
+            ("a/..", "."),
+            ("/../a", "/a"),
+        ] {
+            assert_eq!(
+                normalize_path(Path::new(path)),
+                Path::new(expected),
+                "{}",
+                path
+            );
+        }
+
+        let catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let mut index = Index::new();
+        index.set_canonicalize(true);
+        catalog.index(Path::new("test"), &mut index);
         assert_eq!(
             index.get_path("https://schema.example.com/schema/schema_catalog_schema.json"),
-            Some("test/../vendor/schema_catalog_schema.json".to_string())
+            Some(std::fs::canonicalize("vendor/schema_catalog_schema.json").unwrap())
+        );
+    }
+
+    #[test]
+    fn id_normalization() {
+        let catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let mut index = Index::new();
+        catalog.index(Path::new("test"), &mut index);
+        let expected = Some(PathBuf::from("vendor/schema_catalog_schema.json"));
+        for id in [
+            "https://schema.example.com/schema/schema_catalog_schema.json",
+            "HTTPS://Schema.Example.com/schema/schema_catalog_schema.json",
+            "https://schema.example.com:443/schema/schema_catalog_schema.json",
+            "https://schema.example.com/schema/schema_catalog_schema.json#",
+            "https://schema.example.com/schema/schema_catalog_schema.json#/definitions/catalog",
+            "https://schema.example.com/other/../schema/./schema_catalog_schema.json",
+            "https://schema.example.com/%73chema/schema_catalog_schema.json",
+        ] {
+            assert_eq!(index.get_path(id), expected, "{}", id);
+            assert_eq!(
+                index.resolve(&url::Url::parse(id).unwrap()),
+                expected,
+                "{}",
+                id
+            );
+        }
+        assert_eq!(
+            index.get_path("http://schema.example.com/schema/schema_catalog_schema.json"),
+            None
+        );
+        assert_eq!(
+            uri::normalize_id("https://example.com/a%2fb%c3%a9?q=%7e"),
+            "https://example.com/a%2Fb%C3%A9?q=~"
+        );
+    }
+
+    #[test]
+    fn integrity_policy() {
+        let id = "https://schema.example.com/schema/schema_catalog_schema.json";
+        let mut catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let content = std::fs::read("vendor/schema_catalog_schema.json").unwrap();
+        let integrity = Some(integrity::compute(&content));
+
+        let index_with = |catalog: &Catalog, policy| {
+            let mut index = Index::new();
+            index.set_integrity_policy(policy);
+            catalog.index(Path::new("test"), &mut index);
+            index
+        };
+        let expected = PathBuf::from("vendor/schema_catalog_schema.json");
+        assert!(matches!(
+            index_with(&catalog, IntegrityPolicy::Require).try_get_path(id),
+            Err(Error::MissingIntegrity { .. })
+        ));
+        assert_eq!(
+            index_with(&catalog, IntegrityPolicy::Verify).get_path(id),
+            Some(expected.clone())
+        );
+
+        catalog.groups[0].schemas[0].integrity = integrity;
+        catalog
+            .check(&Default::default(), Path::new("test/example.json"))
+            .unwrap();
+        assert_eq!(
+            index_with(&catalog, IntegrityPolicy::Require).get_path(id),
+            Some(expected.clone())
+        );
+
+        catalog.groups[0].schemas[0].integrity = Some(integrity::compute(b"{}"));
+        let err = catalog
+            .check(&Default::default(), Path::new("test/example.json"))
+            .unwrap_err();
+        assert!(
+            matches!(err.innermost(), Error::IntegrityMismatch { .. }),
+            "{:?}",
+            err
+        );
+        let index = index_with(&catalog, IntegrityPolicy::Verify);
+        assert!(matches!(
+            index.try_get_path(id),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+        assert_eq!(index.get_path(id), None);
+        assert_eq!(
+            index_with(&catalog, IntegrityPolicy::Ignore).get_path(id),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn path_policy() {
+        let confine = PathPolicy {
+            confine: true,
+            max_file_size: None,
+        };
+        let opts = CheckOptions {
+            path_policy: confine,
+            ..Default::default()
+        };
+        let id = "https://schema.example.com/schema/schema_catalog_schema.json";
+        let mut catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let err = catalog
+            .check(&opts, Path::new("test/example.json"))
+            .unwrap_err();
+        assert!(
+            matches!(err.innermost(), Error::OutsideRoot { .. }),
+            "{:?}",
+            err
+        );
+        let mut index = Index::new();
+        index.set_path_policy(confine);
+        catalog.index(Path::new("test"), &mut index);
+        assert!(matches!(
+            index.try_get_path(id),
+            Err(Error::OutsideRoot { .. })
+        ));
+
+        let absolute = std::fs::canonicalize("vendor/schema_catalog_schema.json").unwrap();
+        catalog.groups[0].schemas[0].location = absolute.to_str().unwrap().to_string();
+        let err = catalog
+            .check(&opts, Path::new("test/example.json"))
+            .unwrap_err();
+        assert!(matches!(err.innermost(), Error::AbsoluteLocation { .. }));
+
+        let catalog = Catalog::read(Path::new("test/vendor/catalog.json")).unwrap();
+        catalog
+            .check(&opts, Path::new("test/vendor/catalog.json"))
+            .unwrap();
+        let opts = CheckOptions {
+            path_policy: PathPolicy {
+                confine: true,
+                max_file_size: Some(10),
+            },
+            ..Default::default()
+        };
+        let err = catalog
+            .check(&opts, Path::new("test/vendor/catalog.json"))
+            .unwrap_err();
+        assert!(matches!(err.innermost(), Error::FileTooLarge { .. }));
+
+        #[cfg(unix)]
+        {
+            let dir = crate::test_util::TempDir::new("path-policy");
+            std::os::unix::fs::symlink(&absolute, dir.join("link.json")).unwrap();
+            let mut index = Index::new();
+            index.set_path_policy(confine);
+            Schema {
+                id: id.to_string(),
+                integrity: None,
+                location: "link.json".to_string(),
+            }
+            .index(&dir, ".", &mut index);
+            assert!(matches!(
+                index.try_get_path(id),
+                Err(Error::SymlinkOutsideRoot { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn uri_locations() {
+        let id = "https://schema.example.com/uri/my-schema.json";
+        let file = Path::new("test/uri/catalog.json");
+        let mut catalog = Catalog::read(file).unwrap();
+        catalog.check(&Default::default(), file).unwrap();
+        let index_of = |catalog: &Catalog| {
+            let mut index = Index::new();
+            catalog.index(Path::new("test/uri"), &mut index);
+            index
+        };
+        let expected = PathBuf::from("test/uri/schemas/my schema.json");
+        assert_eq!(index_of(&catalog).get_path(id), Some(expected.clone()));
+
+        let absolute = std::fs::canonicalize(&expected).unwrap();
+        let url = url::Url::from_file_path(&absolute).unwrap();
+        assert_eq!(path_to_location(&absolute).unwrap(), url.as_str());
+        catalog.groups[0].schemas[0].location = url.to_string();
+        catalog.check(&Default::default(), file).unwrap();
+        assert_eq!(index_of(&catalog).get_path(id), Some(absolute));
+
+        catalog.groups[0].base_location = url.join(".").unwrap().to_string();
+        catalog.groups[0].schemas[0].location = "my%20schema.json".to_string();
+        catalog.check(&Default::default(), file).unwrap();
+
+        catalog.groups[0].schemas[0].location = "https://schema.example.com/uri/a.json".to_string();
+        let err = catalog.check(&Default::default(), file).unwrap_err();
+        assert!(
+            matches!(err.innermost(), Error::InvalidLocation { .. }),
+            "{:?}",
+            err
+        );
+        assert!(matches!(
+            index_of(&catalog).try_get_path(id),
+            Err(Error::InvalidLocation { .. })
+        ));
+
+        assert_eq!(
+            location_path("C:\\schemas\\my schema.json").unwrap(),
+            PathBuf::from("C:\\schemas\\my schema.json")
+        );
+        assert_eq!(
+            location_path("d:/schemas/a%20b.json").unwrap(),
+            PathBuf::from("d:/schemas/a%20b.json")
+        );
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(Path::new("test/prefix/schemas"), Path::new("test/include")).unwrap(),
+            Path::new("../prefix/schemas")
+        );
+        assert_eq!(
+            relative_path(Path::new("test"), Path::new("test/./")).unwrap(),
+            Path::new(".")
+        );
+        assert_eq!(
+            relative_path(Path::new(""), Path::new("test/include/product")).unwrap(),
+            Path::new("../../..")
+        );
+    }
+
+    #[test]
+    fn uri_prefix() {
+        let mut catalog = Catalog::read(Path::new("test/prefix/catalog.json")).unwrap();
+        catalog
+            .check(&Default::default(), Path::new("test/prefix/catalog.json"))
+            .unwrap();
+        let mut index = Index::new();
+        catalog.index(Path::new("test/prefix"), &mut index);
+        assert_eq!(
+            index.get_path("https://schema.example.com/v3/a.json"),
+            Some(PathBuf::from("test/prefix/schemas/a.json"))
+        );
+        assert_eq!(
+            index.get_path("https://schema.example.com/v3/sub/b%20c.json#"),
+            Some(PathBuf::from("test/prefix/schemas/sub/b c.json"))
+        );
+        assert_eq!(
+            index.get_path("https://schema.example.com/v3/nope.json"),
+            None
+        );
+        assert_eq!(
+            index.get_path("https://schema.example.com/v3/../prefix/catalog.json"),
+            None
+        );
+
+        // The id in sub/b c.json does not match this prefix
+        catalog.groups[0].uri_prefix = Some("https://schema.example.com/v4/".to_string());
+        let err = catalog
+            .check(&Default::default(), Path::new("test/prefix/catalog.json"))
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.innermost(),
+                Error::IdMismatch { expected, .. }
+                    if expected == "https://schema.example.com/v4/sub/b%20c.json"
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn reverse_index() {
+        let mut index = Index::new();
+        Catalog::read(Path::new("test/example.json"))
+            .unwrap()
+            .index(Path::new("test"), &mut index);
+        Catalog::read(Path::new("test/prefix/catalog.json"))
+            .unwrap()
+            .index(Path::new("test/prefix"), &mut index);
+        let reverse = index.reverse();
+        assert_eq!(
+            reverse.get_id(Path::new("test/../vendor/schema_catalog_schema.json")),
+            Some("https://schema.example.com/schema/schema_catalog_schema.json".to_string())
+        );
+        assert_eq!(
+            reverse.get_id(&std::fs::canonicalize("test/prefix/schemas/sub/b c.json").unwrap()),
+            Some("https://schema.example.com/v3/sub/b%20c.json".to_string())
+        );
+        assert_eq!(reverse.get_id(Path::new("test/example.json")), None);
+    }
+
+    #[test]
+    fn include() {
+        let catalogs = read_catalogs(["test/include/team.json"]).unwrap();
+        assert_eq!(
+            catalogs
+                .iter()
+                .map(|c| c.file.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "test/include/product/../base.json",
+                "test/include/product/product.yaml",
+                "test/include/team.json"
+            ]
+        );
+        let mut index = Index::new();
+        for c in &catalogs {
+            c.catalog.index(c.dir(), &mut index);
+        }
+        assert_eq!(
+            index.get_path("https://schema.example.com/overridden.json"),
+            Some(PathBuf::from("test/prefix/schemas/a.json"))
+        );
+        assert_eq!(
+            index.get_path("https://schema.example.com/schema/schema_catalog_schema.json"),
+            Some(PathBuf::from("vendor/schema_catalog_schema.json"))
+        );
+        catalogs[2]
+            .catalog
+            .check(&Default::default(), Path::new("test/include/team.json"))
+            .unwrap();
+    }
+
+    #[test]
+    fn include_cycle() {
+        let err = read_catalogs(["test/include/cycle-a.json"]).err().unwrap();
+        assert!(
+            matches!(err.innermost(), Error::IncludeCycle { cycle } if cycle.len() == 3),
+            "{:?}",
+            err
+        );
+
+        let catalog = Catalog::read(Path::new("test/include/cycle-a.json")).unwrap();
+        let err = catalog
+            .check(&Default::default(), Path::new("test/include/cycle-a.json"))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("in catalog "), "{}", err);
+        assert!(
+            matches!(err.innermost(), Error::IncludeCycle { .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn group_from_schema_errors() {
+        let err = group_from_schema(
+            Path::new("test/a.json"),
+            &json!({ "$id": "https://schema.example.com/schema/A.json" }),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, Error::MissingSchemaField { field: "title", .. }),
+            "{:?}",
+            err
         );
+        let err = group_from_schema(Path::new("test/a.json"), &json!({ "title": 1 })).unwrap_err();
+        assert!(matches!(err, Error::NotASchema { .. }), "{:?}", err);
     }
 
     #[test]
     fn grouping() {
         let a = group_from_schema(
-            "test/example.json",
+            Path::new("test/example.json"),
             &json!({
                 "$schema": "http://json-schema.org/draft-07/schema#",
                 "$id": "https://schema.example.com/schema/A.json",
@@ -309,7 +1338,7 @@ mod tests {
             }),
         );
         let b = group_from_schema(
-            "test/example.json",
+            Path::new("test/example.json"),
             &json!({
                 "$schema": "http://json-schema.org/draft-07/schema#",
                 "$id": "https://schema.example.com/schema/B.json",
@@ -317,7 +1346,7 @@ mod tests {
             }),
         );
         let c_a = group_from_schema(
-            "test/c/a.json",
+            Path::new("test/c/a.json"),
             &json!({
                 "$schema": "http://json-schema.org/draft-07/schema#",
                 "$id": "https://schema.example.com/schema/C/A.json",
@@ -325,7 +1354,7 @@ mod tests {
             }),
         );
         let c_b = group_from_schema(
-            "test/cb.json",
+            Path::new("test/cb.json"),
             &json!({
                 "$schema": "http://json-schema.org/draft-07/schema#",
                 "$id": "https://schema.example.com/schema/C/B.json",
@@ -333,7 +1362,7 @@ mod tests {
             }),
         );
         let d = group_from_schema(
-            "test/dee/example.json",
+            Path::new("test/dee/example.json"),
             &json!({
                 "$schema": "http://json-schema.org/draft-07/schema#",
                 "$id": "https://schema.example.com/schema/D.json",
@@ -353,6 +1382,7 @@ mod tests {
             catalog,
             Catalog {
                 name: "foo".to_string(),
+                include: vec![],
                 groups: vec![
                     CatalogGroup {
                         base_location: "test".to_string(),
@@ -360,37 +1390,46 @@ mod tests {
                         schemas: vec![
                             Schema {
                                 id: "https://schema.example.com/schema/A.json".to_string(),
+                                integrity: None,
                                 location: "example.json".to_string()
                             },
                             Schema {
                                 id: "https://schema.example.com/schema/B.json".to_string(),
+                                integrity: None,
                                 location: "example.json".to_string()
                             }
-                        ]
+                        ],
+                        uri_prefix: None,
                     },
                     CatalogGroup {
                         base_location: "test".to_string(),
                         name: "Catalog Cee".to_string(),
                         schemas: vec![Schema {
                             id: "https://schema.example.com/schema/C/B.json".to_string(),
+                            integrity: None,
                             location: "cb.json".to_string()
-                        }]
+                        }],
+                        uri_prefix: None,
                     },
                     CatalogGroup {
                         base_location: "test/c".to_string(),
                         name: "Catalog C".to_string(),
                         schemas: vec![Schema {
                             id: "https://schema.example.com/schema/C/A.json".to_string(),
+                            integrity: None,
                             location: "a.json".to_string()
-                        }]
+                        }],
+                        uri_prefix: None,
                     },
                     CatalogGroup {
                         base_location: "test/dee".to_string(),
                         name: "Catalog".to_string(),
                         schemas: vec![Schema {
                             id: "https://schema.example.com/schema/D.json".to_string(),
+                            integrity: None,
                             location: "example.json".to_string()
-                        }]
+                        }],
+                        uri_prefix: None,
                     }
                 ],
             }
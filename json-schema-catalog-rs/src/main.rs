@@ -1,6 +1,18 @@
 use anyhow::{Context as _, Ok, Result};
-use clap::{arg, Parser, Subcommand};
-use json_schema_catalog_rs::{catalog_from_groups, group_from_schema, Catalog, Index};
+use clap::{CommandFactory as _, Parser, Subcommand, ValueEnum};
+use json_schema_catalog_rs::{
+    cache, catalog_from_groups,
+    config::{Config, OutputMode, ReplaceConfig, CONFIG_FILE_NAME},
+    edit, export, fetch,
+    format::{parse_document, DocumentFormat},
+    group_from_schema_file, location_path, normalize_path, path_to_location, read_catalogs,
+    references::{self, DocumentKind, DEFAULT_KEYWORDS},
+    relative_path, schema_id, schemastore,
+    vendor::{self, Copies, PendingCopy, Vendored},
+    xml_catalog, Catalog, CatalogFile, CatalogGroup, Index, IntegrityPolicy, PathPolicy,
+};
+use std::io::{IsTerminal as _, Write as _};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(
@@ -10,148 +22,727 @@ use json_schema_catalog_rs::{catalog_from_groups, group_from_schema, Catalog, In
     author
 )]
 struct Cli {
+    #[command(flatten)]
+    catalogs: CatalogOptions,
+
+    /// Don't look for a project configuration file
+    #[arg(
+        help = "Do not read json-schema-catalog.toml from the current directory or its ancestors",
+        long = "no-config",
+        global = true
+    )]
+    no_config: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Selection of the catalogs that commands look up schemas in.
+#[derive(clap::Args)]
+struct CatalogOptions {
+    /// Catalog files to use
+    #[arg(
+        help = "Catalog file to use. Can be repeated. These are in addition to the ones found in XDG_DATA_HOME, XDG_DATA_DIRS and JSON_SCHEMA_CATALOGS.",
+        long = "catalog",
+        value_name = "FILE",
+        global = true
+    )]
+    catalogs: Vec<PathBuf>,
+
+    /// Don't use the catalogs installed in the XDG data directories
+    #[arg(
+        help = "Do not use catalogs from ${XDG_DATA_HOME,XDG_DATA_DIRS}/json-schema-catalogs/",
+        long = "no-xdg",
+        global = true
+    )]
+    no_xdg: bool,
+
+    /// Use the catalogs installed in the XDG data directories, even if the configuration says not to
+    #[arg(
+        help = "Use catalogs from the XDG data directories, overriding xdg = false in json-schema-catalog.toml",
+        long = "xdg",
+        overrides_with = "no_xdg",
+        global = true
+    )]
+    xdg: bool,
+
+    /// How to treat the integrity hashes of schemas
+    #[arg(
+        help = "How to treat the integrity recorded for schemas when looking them up: ignore (the default), verify the files that have one, or require one for every schema",
+        long = "integrity",
+        value_name = "POLICY",
+        global = true
+    )]
+    integrity: Option<IntegrityPolicy>,
+
+    /// Keep schema files inside the directories of their catalogs
+    #[arg(
+        help = "Refuse schema locations that are absolute, or that .. or symlinks take outside of the directory of their catalog file. Use this for catalogs that are not trusted.",
+        long = "confine",
+        global = true
+    )]
+    confine: bool,
+
+    /// Maximum size of schema files
+    #[arg(
+        help = "Refuse schema files larger than this many bytes",
+        long = "max-file-size",
+        value_name = "BYTES",
+        global = true
+    )]
+    max_file_size: Option<u64>,
+
+    /// Don't use the index cache
+    #[arg(
+        help = "Parse all catalogs instead of using the index cached in ${XDG_CACHE_HOME}/json-schema-catalog/, and don't update the cache",
+        long = "no-cache",
+        global = true
+    )]
+    no_cache: bool,
+}
+impl CatalogOptions {
+    /// The path policy from the command line, falling back to the configuration.
+    fn path_policy(&self, config: &Config) -> PathPolicy {
+        let configured = config.paths.path_policy();
+        PathPolicy {
+            confine: self.confine || configured.confine,
+            max_file_size: self.max_file_size.or(configured.max_file_size),
+        }
+    }
+
+    /// The catalog files to use, from lowest to highest precedence: XDG data
+    /// directories, `JSON_SCHEMA_CATALOGS`, the project configuration, `--catalog`
+    /// and finally `extra_files`.
+    fn files(&self, config: &Config, extra_files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        let use_xdg = if self.no_xdg || self.xdg {
+            self.xdg
+        } else {
+            config.xdg.unwrap_or(true)
+        };
+        if use_xdg {
+            for entry in xdg::BaseDirectories::new().list_data_files_once("json-schema-catalogs") {
+                if is_installed_catalog(&entry) {
+                    files.push(entry);
+                }
+            }
+        }
+        if let Some(paths) = std::env::var_os("JSON_SCHEMA_CATALOGS") {
+            for path in std::env::split_paths(&paths) {
+                if !path.as_os_str().is_empty() {
+                    files.push(path);
+                }
+            }
+        }
+        files.extend(config.catalogs.iter().cloned());
+        files.extend(self.catalogs.iter().cloned());
+        files.extend(extra_files.iter().cloned());
+        Ok(files)
+    }
+}
+
+/// Whether a file in a `json-schema-catalogs` data directory is a catalog: `*.json`,
+/// or `*.catalog.<ext>` for the other formats, so that other files that happen to
+/// be installed there, such as a `Cargo.toml`, are not taken for catalogs.
+fn is_installed_catalog(path: &Path) -> bool {
+    match DocumentFormat::from_extension(path) {
+        Some(DocumentFormat::Json) => true,
+        Some(_) => path
+            .file_stem()
+            .is_some_and(|stem| Path::new(stem).extension() == Some("catalog".as_ref())),
+        None => false,
+    }
+}
+
+/// Print a path on a line of its own. On Unix, the bytes of the path are written
+/// as they are, so that paths that aren't valid UTF-8 come out unchanged.
+fn print_path(path: &Path) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    #[cfg(unix)]
+    stdout.write_all(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()))?;
+    #[cfg(not(unix))]
+    write!(stdout, "{}", path.display())?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Check a JSON schema catalog file for validity
     Check(CheckCommand),
-    /// Look up a schema location by its id in a JSON schema catalog file
+    /// Look up schema locations by their ids in the JSON schema catalogs
     Lookup(LookupCommand),
     /// Replace "$ref", "$schema" occurrences in a JSON file with the corresponding physical file location
     Replace(ReplaceCommand),
+    /// Replace references to catalogued schema files with the schema ids, undoing "replace"
+    Canonicalize(CanonicalizeCommand),
     /// Create a new JSON Schema Catalog file from a set of JSON schema files
     New(NewCommand),
+    /// Add JSON schema files to an existing JSON Schema Catalog file
+    Add(AddCommand),
+    /// Convert a JSON Schema Catalog file between JSON, JSONC, YAML and TOML
+    Convert(ConvertCommand),
+    /// Create a JSON Schema Catalog from a SchemaStore catalog.json and a directory of downloaded schemas
+    ImportSchemastore(ImportSchemastoreCommand),
+    /// Export the catalogs as editor schema associations or as a SchemaStore catalog
+    Export(ExportCommand),
+    /// Copy schemas and the schemas they reference into a directory, with a catalog for them
+    Vendor(VendorCommand),
+    /// Download schemas and the schemas they reference over HTTP(S) into a directory, with a catalog for them
+    Fetch(FetchCommand),
+    /// Create a JSON Schema Catalog from an OASIS XML Catalog
+    ImportXmlCatalog(ImportXmlCatalogCommand),
+    /// Write a JSON Schema Catalog as an OASIS XML Catalog
+    ExportXmlCatalog(ExportXmlCatalogCommand),
 }
 
 #[derive(Parser)]
 struct CheckCommand {
     /// Path to the catalog file
     #[arg(
-        help = "Path to the JSON schema catalog file. This checks the individual files for being valid JSON, but NOT for being a valid schema."
+        help = "Path to the JSON schema catalog file. This checks the individual files for being valid JSON, JSONC or YAML, but NOT for being a valid schema. If omitted, all selected catalogs are checked."
     )]
-    catalog_file: String,
+    catalog_file: Option<PathBuf>,
 
     #[arg(
         help = "Disable checking for matching schema ids.",
-        long("no-check-schema-id"),
-        default_value = "true"
+        long = "no-check-schema-id"
     )]
-    require_matching_id: bool,
+    no_check_schema_id: bool,
+
+    #[arg(
+        help = "Enable checking for matching schema ids, overriding schema-id = false in json-schema-catalog.toml",
+        long = "check-schema-id",
+        overrides_with = "no_check_schema_id"
+    )]
+    check_schema_id: bool,
 }
 impl CheckCommand {
-    fn run(&self) -> Result<()> {
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        let require_matching_id = if self.no_check_schema_id || self.check_schema_id {
+            self.check_schema_id
+        } else {
+            config.check.schema_id.unwrap_or(true)
+        };
         let opts = json_schema_catalog_rs::CheckOptions {
-            require_matching_id: true,
+            require_matching_id,
+            path_policy: catalogs.path_policy(config),
         };
-        let catalog: Catalog = serde_json::from_str(&std::fs::read_to_string(&self.catalog_file)?)?;
-        catalog.check(&opts, &self.catalog_file)?;
+        let files = match &self.catalog_file {
+            Some(file) => vec![file.clone()],
+            None => catalogs.files(config, &[])?,
+        };
+        for file in &files {
+            let catalog = Catalog::read(Path::new(file))?;
+            catalog.check(&opts, file)?;
+        }
         Ok(())
     }
 }
 
 struct Context {
-    catalogs: Vec<(String, Catalog)>,
+    /// Empty if the index came from the cache
+    catalogs: Vec<CatalogFile>,
     index: Index,
 }
 impl Context {
-    fn new(extra_files: &Vec<String>, verbose: bool) -> Result<Self> {
-        let xdg_catalogs = xdg::BaseDirectories::new()
-            .list_data_files_once("json-schema-catalogs")
-            .iter()
-            .flat_map(|path| {
-                Some(path).into_iter().flat_map(move |entry| {
-                    if entry.extension().map_or(false, |ext| ext == "json") {
-                        Some(entry.to_str().unwrap().to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect::<Vec<String>>();
+    /// Load the index, from the cache if it is up to date and not disabled.
+    fn new(
+        options: &CatalogOptions,
+        config: &Config,
+        extra_files: &[PathBuf],
+        verbose: bool,
+    ) -> Result<Self> {
+        Self::load(options, config, extra_files, verbose, !options.no_cache)
+    }
 
-        let mut catalog_files = xdg_catalogs;
-        catalog_files.extend(extra_files.iter().cloned());
+    /// Parse the catalogs, for commands that need more than the index.
+    fn with_catalogs(
+        options: &CatalogOptions,
+        config: &Config,
+        extra_files: &[PathBuf],
+        verbose: bool,
+    ) -> Result<Self> {
+        Self::load(options, config, extra_files, verbose, false)
+    }
 
-        let catalogs = catalog_files
+    fn load(
+        options: &CatalogOptions,
+        config: &Config,
+        extra_files: &[PathBuf],
+        verbose: bool,
+        use_cache: bool,
+    ) -> Result<Self> {
+        // Absolute paths make the index, and so its cache, independent of the
+        // current directory
+        let catalog_files = options
+            .files(config, extra_files)?
             .iter()
-            .map(|file| {
-                if verbose {
-                    eprintln!("Parsing catalog {:?}", file);
-                }
-                let c: Catalog = serde_json::from_str(&std::fs::read_to_string(&file)?)?;
-                // Get the directory of the file
-                let dir = std::path::Path::new(file)
-                    .parent()
-                    .with_context(|| format!("Could not get parent directory of {}", file))?;
-                // Convert to string
-                let dir = dir.to_str().with_context(|| {
-                    format!("Could not convert parent directory of {} to string", file)
-                })?;
+            .map(|file| Ok(normalize_path(&std::path::absolute(file)?)))
+            .collect::<Result<Vec<_>>>()?;
 
-                Ok((dir.to_string(), c))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let cache_file = match xdg::BaseDirectories::new().get_cache_home() {
+            Some(dir) if use_cache => Some(cache::cache_file(
+                &dir.join("json-schema-catalog"),
+                &catalog_files,
+            )),
+            _ => None,
+        };
+        let cached = cache_file
+            .as_deref()
+            .and_then(|file| cache::load(file, &catalog_files));
 
-        let mut r = Self {
-            catalogs,
-            index: Index::new(),
+        let mut r = match cached {
+            Some(index) => {
+                if verbose {
+                    eprintln!(
+                        "Using the cached index of {} catalog(s)",
+                        catalog_files.len()
+                    );
+                }
+                Self {
+                    catalogs: vec![],
+                    index,
+                }
+            }
+            None => {
+                if verbose {
+                    for file in &catalog_files {
+                        eprintln!("Parsing catalog {:?}", file);
+                    }
+                }
+                let mut r = Self {
+                    catalogs: read_catalogs(&catalog_files)?,
+                    index: Index::new(),
+                };
+                for catalog_file in &r.catalogs {
+                    if verbose && !catalog_files.contains(&catalog_file.file) {
+                        eprintln!("Parsed included catalog {:?}", catalog_file.file);
+                    }
+                    catalog_file.catalog.index(catalog_file.dir(), &mut r.index);
+                }
+                if let Some(file) = &cache_file {
+                    // The cache is only an optimization, so failing to write it is not an error
+                    if let Err(e) = cache::store(file, &catalog_files, &r.catalogs, &r.index) {
+                        if verbose {
+                            eprintln!("Could not cache the index: {:#}", anyhow::Error::from(e));
+                        }
+                    }
+                }
+                r
+            }
         };
-        for (dir, catalog) in &r.catalogs {
-            catalog.index(dir, &mut r.index);
-        }
+        r.index
+            .set_integrity_policy(options.integrity.or(config.integrity).unwrap_or_default());
+        r.index.set_path_policy(options.path_policy(config));
         Ok(r)
     }
 }
 
 #[derive(Parser)]
 struct LookupCommand {
-    /// The schema id to look up
-    #[arg(help = "The schema id to look up")]
-    schema_id: String,
+    /// The schema ids to look up
+    #[arg(
+        help = "The schema ids to look up. For -, or without any when standard input is not a terminal, ids are read from standard input, one per line. For compatibility, arguments after the first that name existing catalog files are used as catalogs, with a warning."
+    )]
+    schema_ids: Vec<String>,
+
+    /// Extra catalog files
+    #[arg(
+        help = "Extra catalog files to use. Equivalent to --catalog. This checks the individual files for being valid JSON, but NOT for being a valid schema. Files can also be passed via XDG_DATA_HOME and XDG_DATA_DIRS, as per ${dirs}/json-schema-catalogs/*.json and *.catalog.{jsonc,yaml,yml,toml}",
+        long = "extra-catalogs"
+    )]
+    catalog_files: Vec<PathBuf>,
+
+    /// Print JSON
+    #[arg(
+        help = "Print a JSON object that maps each id to its path, or to null if it is not found",
+        long
+    )]
+    json: bool,
 
-    /// Path to the catalog files
+    /// Don't stop at unknown ids
     #[arg(
-        help = "Path to the JSON schema catalog file. This checks the individual files for being valid JSON, but NOT for being a valid schema. Files can also be passed via XDG_DATA_HOME and XDG_DATA_DIRS, as per ${dirs}/json-schema-catalogs/*.json"
+        help = "Report ids that are not found, or refused, and continue with the others. Prints an empty line or null for them, and fails at the end.",
+        long = "keep-going"
     )]
-    catalog_files: Vec<String>,
+    keep_going: bool,
 
     /// Verbose output
     #[arg(help = "Enable verbose output", long, default_value = "false")]
     verbose: bool,
+
+    /// Resolve symlinks
+    #[arg(
+        help = "Print the canonical, absolute path with symlinks resolved",
+        long
+    )]
+    canonicalize: bool,
 }
 impl LookupCommand {
-    fn run(&self) -> Result<()> {
-        let context = Context::new(&self.catalog_files, self.verbose)?;
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        // Waiting for ids typed into a terminal would look like a hang
+        if self.schema_ids.is_empty() && std::io::stdin().is_terminal() {
+            let mut command = Cli::command();
+            command.build();
+            command
+                .find_subcommand_mut("lookup")
+                .expect("lookup is a subcommand")
+                .error(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    "the following required arguments were not provided:\n  <SCHEMA_IDS>...",
+                )
+                .exit();
+        }
+        // Catalog files used to be passed after the id, as in `lookup <id> [catalog_files...]`
+        let mut schema_ids = vec![];
+        let mut catalog_files = self.catalog_files.clone();
+        for (i, arg) in self.schema_ids.iter().enumerate() {
+            let path = Path::new(arg);
+            if i > 0 && DocumentFormat::from_extension(path).is_some() && path.is_file() {
+                eprintln!(
+                    "Warning: using {} as a catalog file. Passing catalog files after the schema id is deprecated; use --extra-catalogs instead.",
+                    arg
+                );
+                catalog_files.push(path.to_path_buf());
+            } else {
+                schema_ids.push(arg.clone());
+            }
+        }
 
-        let schema = context
-            .index
-            .get_path(self.schema_id.as_str())
-            .ok_or_else(|| {
-                anyhow::anyhow!("Schema with id {} not found in catalog", self.schema_id)
-            })?;
-        println!("{}", &schema);
+        let mut context = Context::new(catalogs, config, &catalog_files, self.verbose)?;
+        context.index.set_canonicalize(self.canonicalize);
+
+        let mut ids = vec![];
+        for id in &schema_ids {
+            if id == "-" {
+                ids.extend(read_ids()?);
+            } else {
+                ids.push(id.clone());
+            }
+        }
+        if schema_ids.is_empty() {
+            ids = read_ids()?;
+        }
+
+        let mut json = serde_json::Map::new();
+        let mut failed = 0;
+        for id in ids {
+            let path = match lookup(&context.index, &id) {
+                Result::Ok(path) => Some(path),
+                Err(e) if self.keep_going => {
+                    eprintln!("{:#}", e);
+                    failed += 1;
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            if self.json {
+                let path = match path {
+                    Some(path) => serde_json::Value::String(
+                        path.to_str()
+                            .with_context(|| format!("Path is not UTF-8: {}", path.display()))?
+                            .to_string(),
+                    ),
+                    None => serde_json::Value::Null,
+                };
+                json.insert(id, path);
+            } else {
+                // An empty line keeps the output aligned with the ids
+                print_path(path.as_deref().unwrap_or(Path::new("")))?;
+            }
+        }
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        if failed > 0 {
+            anyhow::bail!("Could not look up {} schema id(s)", failed);
+        }
+        Ok(())
+    }
+}
+
+/// Look up the file of a schema.
+fn lookup(index: &Index, id: &str) -> Result<PathBuf> {
+    // Ids that aren't URLs can only be matched exactly
+    let schema = match url::Url::parse(id) {
+        Result::Ok(url) => index.try_resolve(&url)?,
+        Err(_) => index.try_get_path(id)?,
+    };
+    schema.ok_or_else(|| anyhow::anyhow!("Schema with id {} not found in catalog", id))
+}
+
+/// Read ids from standard input, one per line, skipping empty lines.
+fn read_ids() -> Result<Vec<String>> {
+    let input =
+        std::io::read_to_string(std::io::stdin()).context("Could not read standard input")?;
+    Ok(input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Where commands that rewrite documents write their results.
+#[derive(Clone, clap::Args)]
+struct OutputOptions {
+    /// Write to standard output
+    #[arg(
+        help = "Write the result to standard output. This is the default, unless json-schema-catalog.toml sets an output.",
+        long,
+        conflicts_with_all = ["in_place", "output_dir", "check"]
+    )]
+    stdout: bool,
+
+    /// Overwrite the input files
+    #[arg(
+        help = "Write the results back to the input files",
+        long = "in-place",
+        conflicts_with_all = ["output_dir", "check"]
+    )]
+    in_place: bool,
+
+    /// Output directory
+    #[arg(
+        help = "Write the results into this directory, at the paths of the input files relative to the deepest directory that contains them all",
+        long = "output-dir",
+        value_name = "DIR",
+        conflicts_with = "check"
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Check that the files are up to date
+    #[arg(
+        help = "Don't write anything, but fail if any of the files would change",
+        long
+    )]
+    check: bool,
+}
+impl OutputOptions {
+    /// The output options, with those of the project configuration filled in if
+    /// the command line doesn't choose an output.
+    fn configured(&self, config: &ReplaceConfig) -> Result<OutputOptions> {
+        let mut options = self.clone();
+        if self.stdout || self.in_place || self.output_dir.is_some() || self.check {
+            return Ok(options);
+        }
+        match (config.output, &config.output_dir) {
+            (Some(_), Some(_)) => anyhow::bail!(
+                "Set either output or output-dir in the [replace] section of {}, not both",
+                CONFIG_FILE_NAME
+            ),
+            (None, Some(dir)) => options.output_dir = Some(dir.clone()),
+            (Some(OutputMode::InPlace), None) => options.in_place = true,
+            (Some(OutputMode::Check), None) => options.check = true,
+            (Some(OutputMode::Stdout) | None, None) => {}
+        }
+        Ok(options)
+    }
 
+    /// The file that each input file is written to, or compared with for `--check`.
+    /// `None` for standard output.
+    fn targets(&self, files: &[PathBuf]) -> Result<Vec<Option<PathBuf>>> {
+        if let Some(dir) = &self.output_dir {
+            let base = common_dir(files)?;
+            return files
+                .iter()
+                .map(|file| Ok(Some(dir.join(mirrored_path(file, &base)?))))
+                .collect();
+        }
+        let in_place = self.in_place || self.check;
+        Ok(files
+            .iter()
+            .map(|file| (in_place && file != Path::new("-")).then(|| file.clone()))
+            .collect())
+    }
+
+    /// Read each file, or standard input for `-`, rewrite it with `rewrite` and
+    /// write the result in the same format. `rewrite` is also given the target of
+    /// the file, see [`OutputOptions::targets`].
+    fn rewrite_files(
+        &self,
+        files: &[PathBuf],
+        verbose: bool,
+        mut rewrite: impl FnMut(&Path, Option<&Path>, &mut serde_json::Value) -> Result<()>,
+    ) -> Result<()> {
+        let to_stdout = !self.in_place && self.output_dir.is_none() && !self.check;
+        if to_stdout && files.len() > 1 {
+            anyhow::bail!(
+                "Cannot write more than one file to standard output; pass --in-place, --output-dir or --check"
+            );
+        }
+        if !to_stdout && !self.check && files.iter().any(|file| file == Path::new("-")) {
+            anyhow::bail!("Standard input can only be written to standard output or checked");
+        }
+
+        let targets = self.targets(files)?;
+        let mut changed = 0;
+        for (file, target) in files.iter().zip(&targets) {
+            let stdin = file == Path::new("-");
+            let content = if stdin {
+                std::io::read_to_string(std::io::stdin())
+                    .context("Could not read standard input")?
+            } else {
+                std::fs::read_to_string(file)
+                    .with_context(|| format!("Could not read {}", file.display()))?
+            };
+            let (format, original) = parse_document(file, &content)?;
+            let mut value = original.clone();
+            rewrite(file, target.as_deref(), &mut value)?;
+            let text = updated_text(file, format, &content, &original, &value)?;
+
+            if self.check {
+                if text != content {
+                    eprintln!("{} would change", file.display());
+                    changed += 1;
+                }
+            } else if let Some(target) = target {
+                write_file(target, &text, verbose)?;
+            } else {
+                print!("{}", text);
+            }
+        }
+        if changed > 0 {
+            anyhow::bail!("{} file(s) would change", changed);
+        }
         Ok(())
     }
 }
 
+/// The text of a rewritten document. Only the strings that changed are replaced,
+/// unless the document can't be edited that way, in which case it is serialized
+/// from scratch, with a warning that `file` loses its formatting.
+fn updated_text(
+    file: &Path,
+    format: DocumentFormat,
+    content: &str,
+    original: &serde_json::Value,
+    updated: &serde_json::Value,
+) -> Result<String> {
+    if let Some(text) = edit::update_text(format, content, original, updated) {
+        return Ok(text);
+    }
+    eprintln!(
+        "Warning: could not edit {} in place, so it is written from scratch, without its comments and formatting",
+        file.display()
+    );
+    let mut text = format.to_string_pretty(updated)?;
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Write a file, creating its directory if needed.
+fn write_file(target: &Path, text: &str, verbose: bool) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create {}", parent.display()))?;
+    }
+    std::fs::write(target, text)
+        .with_context(|| format!("Could not write {}", target.display()))?;
+    if verbose {
+        eprintln!("Wrote {}", target.display());
+    }
+    Ok(())
+}
+
+/// The deepest directory that contains all of the files, as an absolute path.
+fn common_dir(files: &[PathBuf]) -> Result<PathBuf> {
+    let mut common: Option<PathBuf> = None;
+    for file in files {
+        let file = normalize_path(&std::path::absolute(file)?);
+        let dir = file.parent().unwrap_or(&file);
+        common = Some(match common {
+            Some(common) => common
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+            None => dir.to_path_buf(),
+        });
+    }
+    Ok(common.unwrap_or_default())
+}
+
+/// The path of an input file relative to `base`, from [`common_dir`], for writing
+/// it into an output directory.
+fn mirrored_path(file: &Path, base: &Path) -> Result<PathBuf> {
+    let file = normalize_path(&std::path::absolute(file)?);
+    let relative = file
+        .strip_prefix(base)
+        .with_context(|| format!("{} is not in {}", file.display(), base.display()))?;
+    Ok(relative.to_path_buf())
+}
+
+/// Call `f` with the keyword and value of every reference in a document, as found
+/// by [`references::find_references`], and replace the value with what `f` returns,
+/// if anything. Without a `kind`, it is detected from the document.
+fn for_each_reference(
+    document: &mut serde_json::Value,
+    kind: Option<DocumentKind>,
+    keywords: &[String],
+    f: &mut dyn FnMut(&str, &str) -> Result<Option<String>>,
+) -> Result<()> {
+    let kind = kind.unwrap_or_else(|| DocumentKind::detect(document));
+    let references = references::find_references(document, kind, keywords)?
+        .into_iter()
+        .map(|r| (r.keyword.to_string(), r.value.to_string(), r.pointer))
+        .collect::<Vec<_>>();
+    for (keyword, reference, pointer) in references {
+        if let Some(replacement) = f(&keyword, &reference)? {
+            if let Some(value) = document.pointer_mut(&pointer) {
+                *value = serde_json::Value::String(replacement);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The keywords to rewrite: those on the command line, or else those in the
+/// project configuration, or else `default`.
+fn keywords(arg: &[String], config: &Config, default: &[&str]) -> Vec<String> {
+    if !arg.is_empty() {
+        arg.to_vec()
+    } else {
+        config
+            .replace
+            .keywords
+            .clone()
+            .unwrap_or_else(|| default.iter().map(|k| k.to_string()).collect())
+    }
+}
+
+/// The keywords that `replace` and `canonicalize` rewrite by default: the references
+/// of [`DEFAULT_KEYWORDS`], and the metaschema.
+const REWRITE_KEYWORDS: [&str; 4] = [
+    DEFAULT_KEYWORDS[0],
+    DEFAULT_KEYWORDS[1],
+    DEFAULT_KEYWORDS[2],
+    "$schema",
+];
+
+const DOCUMENT_HELP: &str = "What kind of documents the files are: schema, to only rewrite references where a schema is expected according to the draft in $schema; openapi, to rewrite references anywhere except in examples and default values; or any, to rewrite every occurrence of the keywords. Defaults to openapi for documents with an openapi or swagger field, and schema otherwise.";
+
 #[derive(Parser)]
 struct ReplaceCommand {
     /// Path to the catalog file
     #[arg(
-        help = "Path to JSON file(s) in which to replace id occurrences with the corresponding physical file location."
+        help = "Path to JSON, JSONC or YAML file(s) in which to replace id occurrences with the corresponding physical file location, or - for standard input. Output is written in the same format as the input, to standard output unless --in-place, --output-dir or --check is passed."
     )]
-    json_files: Vec<String>,
+    json_files: Vec<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputOptions,
 
     /// Extra catalog files
     #[arg(
-        help = "Extra catalog files to use. These are in addition to the ones found in XDG_DATA_HOME and XDG_DATA_DIRS.",
+        help = "Extra catalog files to use. Equivalent to --catalog.",
         long = "extra-catalogs"
     )]
-    extra_catalogs: Vec<String>,
+    extra_catalogs: Vec<PathBuf>,
 
     /// Verbose output
     #[arg(help = "Enable verbose output", long, default_value = "false")]
@@ -160,123 +751,372 @@ struct ReplaceCommand {
     /// Ignore URIs for which we cannot find a schema
     #[arg(
         help = "Ignore URIs for which we cannot find a schema",
-        long = "ignore-unknown",
-        default_value = "false"
+        long = "ignore-unknown"
+    )]
+    ignore_unknown: bool,
+
+    /// Fail on URIs for which we cannot find a schema
+    #[arg(
+        help = "Fail on URIs for which we cannot find a schema, overriding ignore-unknown = true in json-schema-catalog.toml",
+        long = "no-ignore-unknown",
+        overrides_with = "ignore_unknown"
+    )]
+    no_ignore_unknown: bool,
+
+    /// Object keys whose values are replaced
+    #[arg(
+        help = "Object key whose value is a schema reference to replace. Can be repeated. Defaults to $ref, $dynamicRef, $recursiveRef and $schema.",
+        long = "keyword",
+        value_name = "KEY"
+    )]
+    keywords: Vec<String>,
+
+    /// Where references are looked for
+    #[arg(
+        help = DOCUMENT_HELP,
+        long = "document",
+        value_name = "KIND"
+    )]
+    document: Option<DocumentKind>,
+
+    /// Resolve symlinks
+    #[arg(help = "Replace with canonical paths, with symlinks resolved", long)]
+    canonicalize: bool,
+
+    /// Write relative references
+    #[arg(
+        help = "Replace with URI references relative to the file that the output is written to, instead of absolute file: URLs. For standard output, pass --relative-to instead.",
+        long
+    )]
+    relative: bool,
+
+    /// Write references relative to a directory
+    #[arg(
+        help = "Replace with URI references relative to DIR, the directory that the output on standard output will be read from, instead of absolute file: URLs",
+        long = "relative-to",
+        value_name = "DIR",
+        conflicts_with = "relative"
+    )]
+    relative_to: Option<PathBuf>,
+
+    /// Also copy the referenced schemas
+    #[arg(
+        help = "Also copy the schemas that the files reference, transitively, into the output directory as <host>/<path>, and replace all references between the copies with relative references. References are resolved against the $id of the document that contains them.",
+        long,
+        conflicts_with_all = ["relative", "relative_to"]
     )]
+    recursive: bool,
+}
+
+/// The replace options after merging the command line with the project configuration.
+struct ReplaceSettings {
+    keywords: Vec<String>,
+    document: Option<DocumentKind>,
     ignore_unknown: bool,
 }
+
 impl ReplaceCommand {
-    fn replace(&self, context: &Context, value: &mut serde_json::Value) -> Result<()> {
-        let mut status = Ok(());
-        match value {
-            serde_json::Value::Object(map) => {
-                for (key, value) in map.iter_mut() {
-                    if key == "$ref" {
-                        let value_str = value.as_str().ok_or_else(|| {
-                            anyhow::anyhow!(
-                                "Expected string value for {}, but got {:?}",
-                                key,
-                                value
-                            )
-                        })?;
-                        let base_url = url::Url::parse("file:///")
-                            .with_context(|| "Failed to parse base URL for file scheme")?;
-
-                        let url: url::Url = url::Url::options()
-                            .base_url(Some(&base_url))
-                            .parse(value_str)
-                            .with_context(|| format!("Failed to parse URI: {}", value_str))?;
-
-                        let lookup_result = {
-                            let mut schema_url = url.clone();
-                            schema_url.set_fragment(Some(""));
-                            context.index.get_path(schema_url.as_str()).or_else(|| {
-                                // Maybe the # was forgotten, in either the reference or the catalog
-                                match schema_url.fragment() {
-                                    Some("") => {
-                                        schema_url.set_fragment(None);
-                                    }
-                                    None {} => {
-                                        schema_url.set_fragment(Some(""));
-                                    }
-                                    _ => {}
-                                }
-                                context.index.get_path(schema_url.as_str())
-                            })
-                        };
-
-                        match lookup_result {
-                            Some(location) => {
-                                let mut location_url = url::Url::from_file_path(&location)
-                                    .map_err(|()| {
-                                        anyhow::format_err!(
-                                            "Failed to convert catalog path to URL: {}",
-                                            &location
-                                        )
-                                    })?;
-
-                                location_url.set_fragment(url.fragment());
-
-                                let location_str = location_url.as_str();
-
-                                if self.verbose {
-                                    eprintln!(
-                                        "Replacing {} field, old: {} new: {}",
-                                        key, value, location_str
-                                    );
-                                }
-
-                                *value = serde_json::Value::String(location_str.to_string());
-                            }
-                            None {} => {
-                                if url.scheme() == "file" {
-                                    // already local, great!
-                                } else if !self.ignore_unknown {
-                                    status = Err(anyhow::format_err!(
-                                        "Could not find schema with id {}",
-                                        value.as_str().unwrap()
-                                    ))
-                                } else if self.verbose {
-                                    eprintln!(
-                                        "Ignoring unknown schema id {}",
-                                        value.as_str().unwrap()
-                                    );
-                                }
-                            }
-                        };
-                    } else {
-                        self.replace(context, value)?;
-                    }
+    /// Resolve a reference under `keyword` against `base` and look it up. Unknown ids
+    /// are an error, unless they are ignored or metaschemas in `$schema`, which are
+    /// rarely in a catalog; they give `None`, like references that are already local.
+    fn resolve(
+        &self,
+        context: &Context,
+        settings: &ReplaceSettings,
+        base: &url::Url,
+        keyword: &str,
+        reference: &str,
+    ) -> Result<Option<(url::Url, PathBuf)>> {
+        let url: url::Url = url::Url::options()
+            .base_url(Some(base))
+            .parse(reference)
+            .with_context(|| format!("Failed to parse URI: {}", reference))?;
+
+        match context.index.try_resolve(&url)? {
+            Some(location) => return Ok(Some((url, location))),
+            None => {
+                if url.scheme() == "file" {
+                    // already local, great!
+                } else if !settings.ignore_unknown && keyword != "$schema" {
+                    anyhow::bail!("Could not find schema with id {}", reference);
+                } else if self.verbose {
+                    eprintln!("Ignoring unknown schema id {}", reference);
                 }
             }
-            serde_json::Value::Array(array) => {
-                for value in array.iter_mut() {
-                    self.replace(context, value)?;
-                }
+        };
+        Ok(None)
+    }
+    /// The replacement for a single reference, relative to `dir` if given.
+    fn replace(
+        &self,
+        context: &Context,
+        settings: &ReplaceSettings,
+        dir: Option<&Path>,
+        key: &str,
+        reference: &str,
+    ) -> Result<Option<String>> {
+        let base_url = url::Url::parse("file:///")
+            .with_context(|| "Failed to parse base URL for file scheme")?;
+        let Some((url, location)) = self.resolve(context, settings, &base_url, key, reference)?
+        else {
+            return Ok(None);
+        };
+        let location_str = match dir {
+            Some(dir) => relative_reference(&location, dir, url.fragment())?,
+            None => {
+                let location = std::path::absolute(&location)?;
+                let mut location_url = url::Url::from_file_path(&location).map_err(|()| {
+                    anyhow::format_err!(
+                        "Failed to convert catalog path to URL: {}",
+                        location.display()
+                    )
+                })?;
+                location_url.set_fragment(url.fragment());
+                location_url.to_string()
             }
-            _ => {}
+        };
+
+        if self.verbose {
+            eprintln!(
+                "Replacing {} field, old: {} new: {}",
+                key, reference, location_str
+            );
         }
-        status
+        Ok(Some(location_str))
     }
-    fn run(&self) -> Result<()> {
-        let context = Context::new(&self.extra_catalogs, self.verbose)?;
+    /// Replace the references in a document that is written to `target` with
+    /// references to the copies, and queue the schemas that have not been copied
+    /// yet. `base` is the id of the document, unless it has an `$id`.
+    fn replace_with_copies(
+        &self,
+        context: &Context,
+        settings: &ReplaceSettings,
+        document: &mut serde_json::Value,
+        base: &url::Url,
+        target: &Path,
+        copies: &mut Copies,
+    ) -> Result<()> {
+        let id = schema_id(document).and_then(|id| url::Url::parse(id).ok());
+        if let Some(id) = &id {
+            // References back to the document lead to it, rather than to another copy
+            copies.insert(id, target);
+        }
+        let base = id.unwrap_or_else(|| base.clone());
+        let target_dir = target.parent().unwrap_or(Path::new(""));
+        for_each_reference(
+            document,
+            settings.document,
+            &settings.keywords,
+            &mut |key, reference| {
+                if reference.starts_with('#') {
+                    // within the same document, which stays the same in the copy
+                    return Ok(None);
+                }
+                let Some((url, location)) =
+                    self.resolve(context, settings, &base, key, reference)?
+                else {
+                    return Ok(None);
+                };
+                let copy = copies.target(&url, &location);
+                let replacement = relative_reference(&copy, target_dir, url.fragment())?;
+                if self.verbose {
+                    eprintln!(
+                        "Replacing {} field, old: {} new: {}",
+                        key, reference, replacement
+                    );
+                }
+                Ok(Some(replacement))
+            },
+        )
+    }
+    /// Write the copies that [`ReplaceCommand::replace_with_copies`] queued, which
+    /// may queue more.
+    fn write_copies(
+        &self,
+        context: &Context,
+        settings: &ReplaceSettings,
+        copies: &mut Copies,
+    ) -> Result<()> {
+        while let Some(PendingCopy { id, source, target }) = copies.pop() {
+            let content = std::fs::read_to_string(&source)
+                .with_context(|| format!("Could not read {}", source.display()))?;
+            let (format, original) = parse_document(&source, &content)?;
+            let mut document = original.clone();
+            self.replace_with_copies(context, settings, &mut document, &id, &target, copies)
+                .with_context(|| format!("In schema {}", source.display()))?;
+            let text = updated_text(&source, format, &content, &original, &document)?;
+            write_file(&target, &text, self.verbose)?;
+        }
+        Ok(())
+    }
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        let mut context = Context::new(catalogs, config, &self.extra_catalogs, self.verbose)?;
+        context.index.set_canonicalize(self.canonicalize);
+        let settings = ReplaceSettings {
+            keywords: keywords(&self.keywords, config, &REWRITE_KEYWORDS),
+            document: self.document.or(config.replace.document),
+            ignore_unknown: if self.ignore_unknown || self.no_ignore_unknown {
+                self.ignore_unknown
+            } else {
+                config.replace.ignore_unknown.unwrap_or(false)
+            },
+        };
 
-        for file in &self.json_files {
-            let content = std::fs::read_to_string(file)?;
-            let mut value: serde_json::Value = serde_json::from_str(&content)?;
-            self.replace(&context, &mut value)?;
-            println!("{}", serde_json::to_string_pretty(&value)?);
+        let output = self.output.configured(&config.replace)?;
+
+        if self.recursive {
+            let out_dir = output
+                .output_dir
+                .as_deref()
+                .context("--recursive needs an output directory; pass --output-dir")?;
+            let base_url = url::Url::parse("file:///")
+                .with_context(|| "Failed to parse base URL for file scheme")?;
+            let mut copies = Copies::new(out_dir);
+            output.rewrite_files(&self.json_files, self.verbose, |_file, target, document| {
+                let target = target.context("--recursive can not write to standard output")?;
+                self.replace_with_copies(
+                    &context,
+                    &settings,
+                    document,
+                    &base_url,
+                    target,
+                    &mut copies,
+                )
+            })?;
+            return self.write_copies(&context, &settings, &mut copies);
         }
 
-        Ok(())
+        if self.relative_to.is_some()
+            && (output.in_place || output.output_dir.is_some() || output.check)
+        {
+            anyhow::bail!(
+                "--relative-to only applies to standard output; pass --relative to write references relative to each output file"
+            );
+        }
+        output.rewrite_files(&self.json_files, self.verbose, |_file, target, value| {
+            let dir = match (&self.relative_to, target) {
+                (Some(dir), _) => Some(dir.as_path()),
+                (None, Some(target)) if self.relative => {
+                    Some(target.parent().unwrap_or(Path::new("")))
+                }
+                (None, None) if self.relative => anyhow::bail!(
+                    "--relative needs the file that the output is written to; pass --relative-to DIR for standard output"
+                ),
+                (None, _) => None,
+            };
+            for_each_reference(
+                value,
+                settings.document,
+                &settings.keywords,
+                &mut |key, reference| self.replace(&context, &settings, dir, key, reference),
+            )
+        })
+    }
+}
+
+/// A URI reference to `location` relative to the directory `dir`, with a fragment.
+fn relative_reference(location: &Path, dir: &Path, fragment: Option<&str>) -> Result<String> {
+    let mut reference = path_to_location(&relative_path(location, dir)?)?;
+    if let Some(fragment) = fragment {
+        reference.push('#');
+        reference.push_str(fragment);
+    }
+    Ok(reference)
+}
+
+#[derive(Parser)]
+struct CanonicalizeCommand {
+    /// Files to rewrite
+    #[arg(
+        help = "Path to JSON, JSONC or YAML file(s) in which to replace references to catalogued schema files with the schema ids, or - for standard input. Output is written in the same format as the input, to standard output unless --in-place, --output-dir or --check is passed."
+    )]
+    json_files: Vec<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputOptions,
+
+    /// Extra catalog files
+    #[arg(
+        help = "Extra catalog files to use. Equivalent to --catalog.",
+        long = "extra-catalogs"
+    )]
+    extra_catalogs: Vec<PathBuf>,
+
+    /// Verbose output
+    #[arg(help = "Enable verbose output", long, default_value = "false")]
+    verbose: bool,
+
+    /// Object keys whose values are replaced
+    #[arg(
+        help = "Object key whose value is a schema reference to restore. Can be repeated. Defaults to $ref, $dynamicRef, $recursiveRef and $schema.",
+        long = "keyword",
+        value_name = "KEY"
+    )]
+    keywords: Vec<String>,
+
+    /// Where references are looked for
+    #[arg(
+        help = DOCUMENT_HELP,
+        long = "document",
+        value_name = "KIND"
+    )]
+    document: Option<DocumentKind>,
+
+    /// Base directory for relative references
+    #[arg(
+        help = "Resolve relative references against DIR instead of the directory of each input file, as for output written by replace --relative-to DIR",
+        long = "relative-to",
+        value_name = "DIR"
+    )]
+    relative_to: Option<PathBuf>,
+}
+impl CanonicalizeCommand {
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        let context = Context::new(catalogs, config, &self.extra_catalogs, self.verbose)?;
+        let reverse = context.index.reverse();
+        let keywords = keywords(&self.keywords, config, &REWRITE_KEYWORDS);
+        let document = self.document.or(config.replace.document);
+
+        self.output
+            .rewrite_files(&self.json_files, self.verbose, |file, _target, value| {
+                let dir = match &self.relative_to {
+                    Some(dir) => dir.as_path(),
+                    None if file == Path::new("-") => Path::new(""),
+                    None => file.parent().unwrap_or(Path::new("")),
+                };
+                for_each_reference(value, document, &keywords, &mut |key, reference| {
+                    let (location, fragment) = match reference.split_once('#') {
+                        Some((location, fragment)) => (location, Some(fragment)),
+                        None => (reference, None),
+                    };
+                    if location.is_empty() {
+                        return Ok(None);
+                    }
+                    // Only relative references and file: URLs point at local files
+                    let Some(path) = location_path(location).ok() else {
+                        return Ok(None);
+                    };
+                    let Some(mut id) = reverse.get_id(&dir.join(path)) else {
+                        return Ok(None);
+                    };
+                    if let Some(fragment) = fragment {
+                        id.push('#');
+                        id.push_str(fragment);
+                    }
+                    if self.verbose {
+                        eprintln!("Restoring {} field, old: {} new: {}", key, reference, id);
+                    }
+                    Ok(Some(id))
+                })
+            })
     }
 }
 
 #[derive(Parser)]
 struct NewCommand {
     /// Path to the catalog file
-    #[arg(help = "Paths to JSON Schema files")]
-    schema_files: Vec<String>,
+    #[arg(help = "Paths to JSON Schema files, in JSON, JSONC or YAML format")]
+    schema_files: Vec<PathBuf>,
 
     /// Set the name of the schema group not to the individual schema file titles,
     /// so that they can be grouped together.
@@ -286,19 +1126,22 @@ struct NewCommand {
     /// Set the catalog name
     #[arg(long = "catalog-name", default_value = "Catalog")]
     catalog_name: String,
+
+    /// Don't record integrity hashes
+    #[arg(
+        help = "Do not record the sha256 integrity of the schema files",
+        long = "no-integrity"
+    )]
+    no_integrity: bool,
 }
 impl NewCommand {
     fn run(&self) -> Result<()> {
-        let mut groups = Vec::new();
-        for file in &self.schema_files {
-            let content = std::fs::read_to_string(file)?;
-            let value: serde_json::Value = serde_json::from_str(&content)?;
-            let mut group = group_from_schema(file, &value)?;
-            if let Some(name) = &self.group_name {
-                group.name = name.clone();
-            }
-            groups.push(group);
-        }
+        let groups = groups_from_schema_files(
+            &self.schema_files,
+            self.group_name.as_deref(),
+            !self.no_integrity,
+            None,
+        )?;
         let catalog = catalog_from_groups(self.catalog_name.clone(), groups)?;
         println!(
             "{}",
@@ -308,14 +1151,559 @@ impl NewCommand {
     }
 }
 
+/// Generate a group for each schema file, for [`NewCommand`] and [`AddCommand`].
+///
+/// If `catalog_dir` is given, base locations are made relative to it; otherwise
+/// they are as given, relative to the current directory.
+fn groups_from_schema_files(
+    files: &[PathBuf],
+    group_name: Option<&str>,
+    integrity: bool,
+    catalog_dir: Option<&Path>,
+) -> Result<Vec<json_schema_catalog_rs::CatalogGroup>> {
+    let mut groups = Vec::new();
+    for file in files {
+        let mut group = group_from_schema_file(file)?;
+        if let Some(name) = group_name {
+            group.name = name.to_string();
+        }
+        if !integrity {
+            group.schemas[0].integrity = None;
+        }
+        if let Some(catalog_dir) = catalog_dir {
+            let dir = relative_path(file.parent().unwrap_or(Path::new("")), catalog_dir)?;
+            group.base_location = path_to_location(&dir)?;
+        }
+        groups.push(group);
+    }
+    Ok(groups)
+}
+
+#[derive(Parser)]
+struct AddCommand {
+    /// Path to the catalog file
+    #[arg(
+        help = "Path to the JSON schema catalog file to update, in JSON, JSONC, YAML or TOML format. It is written back in the same format."
+    )]
+    catalog_file: PathBuf,
+
+    /// Paths to the schema files
+    #[arg(
+        help = "Paths to JSON Schema files, in JSON, JSONC or YAML format. Existing entries with the same ids are replaced.",
+        required = true
+    )]
+    schema_files: Vec<PathBuf>,
+
+    /// Set the name of the schema group not to the individual schema file titles,
+    /// so that they can be grouped together.
+    #[arg(long = "group-name")]
+    group_name: Option<String>,
+
+    /// Don't record integrity hashes
+    #[arg(
+        help = "Do not record the sha256 integrity of the schema files",
+        long = "no-integrity"
+    )]
+    no_integrity: bool,
+}
+impl AddCommand {
+    /// Update the catalog in place: existing entries keep their position, and new
+    /// schemas are appended to the group they belong to, or in a new group at the end.
+    /// The text of the catalog file is kept where possible, see [`updated_text`].
+    fn run(&self) -> Result<()> {
+        let content = std::fs::read_to_string(&self.catalog_file)
+            .with_context(|| format!("Could not read {}", self.catalog_file.display()))?;
+        let (format, original) = parse_document(&self.catalog_file, &content)?;
+        let catalog = Catalog::read(&self.catalog_file)?;
+        let catalog_dir = self.catalog_file.parent().unwrap_or(Path::new(""));
+        let new_groups = groups_from_schema_files(
+            &self.schema_files,
+            self.group_name.as_deref(),
+            !self.no_integrity,
+            Some(catalog_dir),
+        )?;
+
+        let same_group = |a: &CatalogGroup, b: &CatalogGroup| {
+            a.name == b.name && a.base_location == b.base_location && a.uri_prefix == b.uri_prefix
+        };
+        let mut groups = catalog.groups;
+        for new_group in new_groups {
+            // Replaced schemas move to the group of the new entry
+            for group in groups.iter_mut().filter(|g| !same_group(g, &new_group)) {
+                group
+                    .schemas
+                    .retain(|s| new_group.schemas.iter().all(|new| new.id != s.id));
+            }
+            match groups.iter_mut().find(|g| same_group(g, &new_group)) {
+                Some(group) => {
+                    for schema in new_group.schemas {
+                        match group.schemas.iter_mut().find(|s| s.id == schema.id) {
+                            Some(existing) => *existing = schema,
+                            None => group.schemas.push(schema),
+                        }
+                    }
+                }
+                None => groups.push(new_group),
+            }
+        }
+        groups.retain(|g| !g.schemas.is_empty() || g.uri_prefix.is_some());
+
+        let mut updated = original.clone();
+        updated["groups"] = serde_json::to_value(&groups).context("Failed to serialize catalog")?;
+        let text = updated_text(&self.catalog_file, format, &content, &original, &updated)?;
+        std::fs::write(&self.catalog_file, text)
+            .with_context(|| format!("Could not write {}", self.catalog_file.display()))?;
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+struct ConvertCommand {
+    /// Path to the catalog file to convert
+    #[arg(help = "Path to the catalog file to convert, in JSON, JSONC, YAML or TOML format")]
+    catalog_file: String,
+
+    /// Output file
+    #[arg(
+        help = "File to write the converted catalog to. Its extension determines the format, unless --to is given. Defaults to stdout.",
+        long,
+        short
+    )]
+    output: Option<String>,
+
+    /// Output format
+    #[arg(
+        help = "Output format: json, jsonc, yaml or toml. Defaults to the extension of --output, or json.",
+        long
+    )]
+    to: Option<DocumentFormat>,
+}
+impl ConvertCommand {
+    fn run(&self) -> Result<()> {
+        let catalog = Catalog::read(Path::new(&self.catalog_file))?;
+        let format = self
+            .to
+            .or_else(|| {
+                self.output
+                    .as_ref()
+                    .and_then(|o| DocumentFormat::from_extension(Path::new(o)))
+            })
+            .unwrap_or(DocumentFormat::Json);
+        let mut text = format
+            .to_string_pretty(&catalog)
+            .context("Failed to serialize catalog")?;
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        match &self.output {
+            Some(output) => std::fs::write(output, text)
+                .with_context(|| format!("Could not write {}", output))?,
+            None => print!("{}", text),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+struct ImportSchemastoreCommand {
+    /// Path to the SchemaStore catalog
+    #[arg(help = "Path to a SchemaStore-style catalog.json file")]
+    schemastore_catalog: String,
+
+    /// Directory with the downloaded schemas
+    #[arg(
+        help = "Directory containing local copies of the schemas. Each url is looked up as <host>/<path>, <path> and finally by file name.",
+        long = "schemas-dir"
+    )]
+    schemas_dir: String,
+
+    /// Set the catalog name
+    #[arg(long = "catalog-name", default_value = "SchemaStore")]
+    catalog_name: String,
+
+    /// Output file
+    #[arg(
+        help = "File to write the catalog to, in the format of its extension, with the schema locations relative to it. Defaults to stdout, with the locations relative to the current directory.",
+        long,
+        short
+    )]
+    output: Option<PathBuf>,
+}
+impl ImportSchemastoreCommand {
+    fn run(&self) -> Result<()> {
+        let store = schemastore::SchemaStoreCatalog::read(Path::new(&self.schemastore_catalog))?;
+        let schemas_dir = Path::new(&self.schemas_dir);
+        let base_location = match &self.output {
+            Some(output) => relative_path(schemas_dir, output.parent().unwrap_or(Path::new("")))?,
+            None => schemas_dir.to_path_buf(),
+        };
+        let import = schemastore::import(
+            &store,
+            schemas_dir,
+            &path_to_location(&base_location)?,
+            self.catalog_name.clone(),
+        )?;
+        for missing in &import.missing {
+            eprintln!(
+                "No local copy of schema {} ({}) in {}",
+                missing.url, missing.name, self.schemas_dir
+            );
+        }
+        match &self.output {
+            Some(output) => {
+                let format = DocumentFormat::from_extension(output).unwrap_or(DocumentFormat::Json);
+                let mut text = format
+                    .to_string_pretty(&import.catalog)
+                    .context("Failed to serialize catalog")?;
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                write_file(output, &text, false)?;
+            }
+            None => println!(
+                "{}",
+                serde_json::to_string_pretty(&import.catalog)
+                    .context("Failed to serialize catalog")?
+            ),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// VS Code `json.schemas` setting
+    VscodeJson,
+    /// VS Code `yaml.schemas` setting, for the YAML extension
+    VscodeYaml,
+    /// SchemaStore catalog.json
+    Schemastore,
+}
+
+#[derive(Parser)]
+struct ExportCommand {
+    /// Output format
+    #[arg(long, value_enum)]
+    format: ExportFormat,
+
+    /// Extra catalog files
+    #[arg(
+        help = "Extra catalog files to use. Equivalent to --catalog.",
+        long = "extra-catalogs"
+    )]
+    extra_catalogs: Vec<PathBuf>,
+
+    /// File patterns to associate with a schema
+    #[arg(
+        help = "Associate a schema with files matching a glob pattern, written as ID=GLOB. Can be repeated.",
+        long = "file-match",
+        value_name = "ID=GLOB"
+    )]
+    file_match: Vec<String>,
+
+    /// Verbose output
+    #[arg(help = "Enable verbose output", long, default_value = "false")]
+    verbose: bool,
+}
+impl ExportCommand {
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        let context = Context::with_catalogs(catalogs, config, &self.extra_catalogs, self.verbose)?;
+
+        let mut file_matches = std::collections::BTreeMap::<String, Vec<String>>::new();
+        for file_match in &self.file_match {
+            let (id, glob) = file_match.rsplit_once('=').with_context(|| {
+                format!("Expected ID=GLOB for --file-match, got {}", file_match)
+            })?;
+            file_matches
+                .entry(id.to_string())
+                .or_default()
+                .push(glob.to_string());
+        }
+
+        let associations = export::associations(
+            context.catalogs.iter().map(|c| &c.catalog),
+            &context.index,
+            &file_matches,
+        )?;
+        let value = match self.format {
+            ExportFormat::VscodeJson => export::vscode_json_settings(&associations),
+            ExportFormat::VscodeYaml => {
+                for association in export::without_file_match(&associations) {
+                    eprintln!(
+                        "Warning: leaving out {}, as it has no --file-match pattern",
+                        association.id
+                    );
+                }
+                export::vscode_yaml_settings(&associations)
+            }
+            ExportFormat::Schemastore => {
+                serde_json::to_value(export::schemastore_catalog(&associations))?
+            }
+        };
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+struct VendorCommand {
+    /// Schema ids or catalog files
+    #[arg(
+        help = "Ids of the schemas to copy, or catalog files, all of whose schemas are copied. Catalog files given here are also used for lookups.",
+        required = true
+    )]
+    inputs: Vec<String>,
+
+    /// Output directory
+    #[arg(
+        help = "Directory to copy the schemas into. The catalog is written to catalog.json in this directory.",
+        long,
+        short
+    )]
+    output: PathBuf,
+
+    /// Set the catalog name
+    #[arg(long = "catalog-name", default_value = "Vendored")]
+    catalog_name: String,
+
+    /// Ignore references for which we cannot find a schema
+    #[arg(
+        help = "Ignore references for which we cannot find a schema, instead of failing. They are still reported.",
+        long = "ignore-unknown"
+    )]
+    ignore_unknown: bool,
+
+    /// Verbose output
+    #[arg(help = "Enable verbose output", long, default_value = "false")]
+    verbose: bool,
+}
+impl VendorCommand {
+    fn run(&self, catalogs: &CatalogOptions, config: &Config) -> Result<()> {
+        let mut ids = vec![];
+        let mut catalog_files = vec![];
+        for input in &self.inputs {
+            let path = Path::new(input);
+            if path.is_file() {
+                let catalog = Catalog::read(path)?;
+                ids.extend(
+                    catalog
+                        .groups
+                        .iter()
+                        .flat_map(|g| &g.schemas)
+                        .map(|s| s.id.clone()),
+                );
+                catalog_files.push(path.to_path_buf());
+            } else {
+                ids.push(input.clone());
+            }
+        }
+        let context = Context::new(catalogs, config, &catalog_files, self.verbose)?;
+
+        let vendored = vendor::vendor(
+            &context.index,
+            ids.iter().map(|id| id.as_str()),
+            &self.output,
+            self.catalog_name.clone(),
+        )?;
+        write_vendored(&vendored, &self.output, self.ignore_unknown, self.verbose)
+    }
+}
+
+/// Report the unresolved references and write the catalog of a [`vendor::vendor`]
+/// or [`fetch::fetch`] result into its output directory.
+fn write_vendored(
+    vendored: &Vendored,
+    output: &Path,
+    ignore_unknown: bool,
+    verbose: bool,
+) -> Result<()> {
+    for unresolved in &vendored.unresolved {
+        match &unresolved.reason {
+            Some(reason) => eprintln!(
+                "Could not get schema for {} in {}: {}",
+                unresolved.reference, unresolved.from, reason
+            ),
+            None => eprintln!(
+                "Could not find schema for {} in {}",
+                unresolved.reference, unresolved.from
+            ),
+        }
+    }
+    if !vendored.unresolved.is_empty() && !ignore_unknown {
+        anyhow::bail!(
+            "Could not resolve {} reference(s); pass --ignore-unknown to write the catalog anyway",
+            vendored.unresolved.len()
+        );
+    }
+    if verbose {
+        for schema in vendored.catalog.groups.iter().flat_map(|g| &g.schemas) {
+            eprintln!("Copied {} to {}", schema.id, schema.location);
+        }
+    }
+
+    let catalog_file = output.join("catalog.json");
+    let mut text =
+        serde_json::to_string_pretty(&vendored.catalog).context("Failed to serialize catalog")?;
+    text.push('\n');
+    std::fs::write(&catalog_file, text)
+        .with_context(|| format!("Could not write {}", catalog_file.display()))?;
+    Ok(())
+}
+
+#[derive(Parser)]
+struct FetchCommand {
+    /// Schema ids
+    #[arg(
+        help = "Ids of the schemas to download. Their references are downloaded as well.",
+        required = true
+    )]
+    ids: Vec<String>,
+
+    /// Output directory
+    #[arg(
+        help = "Directory to download the schemas into. The catalog is written to catalog.json in this directory.",
+        long,
+        short
+    )]
+    output: PathBuf,
+
+    /// Set the catalog name
+    #[arg(long = "catalog-name", default_value = "Fetched")]
+    catalog_name: String,
+
+    /// Download from a mirror
+    #[arg(
+        help = "Download ids that start with PREFIX from URL instead, e.g. https://json-schema.org/=http://mirror.example.com/json-schema/. Can be repeated; the longest matching prefix is used.",
+        long,
+        value_name = "PREFIX=URL"
+    )]
+    mirror: Vec<String>,
+
+    /// HTTP proxy
+    #[arg(
+        help = "Proxy to download through, e.g. http://proxy.example.com:3128. Defaults to the HTTPS_PROXY, HTTP_PROXY or ALL_PROXY environment variable.",
+        long,
+        value_name = "URL"
+    )]
+    proxy: Option<String>,
+
+    /// Ignore references that cannot be downloaded
+    #[arg(
+        help = "Ignore references that cannot be downloaded, instead of failing. They are still reported.",
+        long = "ignore-unknown"
+    )]
+    ignore_unknown: bool,
+
+    /// Verbose output
+    #[arg(help = "Enable verbose output", long, default_value = "false")]
+    verbose: bool,
+}
+impl FetchCommand {
+    fn run(&self) -> Result<()> {
+        let mut client = fetch::Client::new(self.proxy.as_deref())?;
+        for mirror in &self.mirror {
+            let (prefix, base) = mirror
+                .split_once('=')
+                .with_context(|| format!("Mirror {} is not of the form PREFIX=URL", mirror))?;
+            let base = url::Url::parse(base)
+                .with_context(|| format!("Mirror URL {} is not a valid URL", base))?;
+            client.add_mirror(prefix.to_string(), base);
+        }
+
+        let fetched = fetch::fetch(
+            &client,
+            self.ids.iter().map(|id| id.as_str()),
+            &self.output,
+            self.catalog_name.clone(),
+        )?;
+        write_vendored(&fetched, &self.output, self.ignore_unknown, self.verbose)
+    }
+}
+
+#[derive(Parser)]
+struct ImportXmlCatalogCommand {
+    /// Path to the XML Catalog
+    #[arg(
+        help = "Path to an OASIS XML Catalog file. Locations in the result are relative to the directory of this file. Entries other than uri entries with relative or file: URIs are reported and skipped. nextCatalog entries become includes of the same file names with a .json extension, so the next catalogs have to be imported next to this one."
+    )]
+    xml_catalog: String,
+
+    /// Set the catalog name
+    #[arg(
+        help = "Set the catalog name. Defaults to the comment preceding the <catalog> element, or Catalog.",
+        long = "catalog-name"
+    )]
+    catalog_name: Option<String>,
+}
+impl ImportXmlCatalogCommand {
+    fn run(&self) -> Result<()> {
+        let xml = std::fs::read_to_string(&self.xml_catalog)
+            .with_context(|| format!("Could not read {}", self.xml_catalog))?;
+        let import = xml_catalog::import(&xml, self.catalog_name.clone())
+            .with_context(|| format!("in XML Catalog {}", self.xml_catalog))?;
+        for unsupported in &import.unsupported {
+            eprintln!("Skipping {}", unsupported);
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&import.catalog).context("Failed to serialize catalog")?
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+struct ExportXmlCatalogCommand {
+    /// Path to the catalog file
+    #[arg(
+        help = "Path to the JSON schema catalog file. The XML Catalog should be placed in the same directory."
+    )]
+    catalog_file: String,
+}
+impl ExportXmlCatalogCommand {
+    fn run(&self) -> Result<()> {
+        let catalog = Catalog::read(Path::new(&self.catalog_file))?;
+        let export = xml_catalog::export(&catalog)?;
+        for unsupported in &export.unsupported {
+            eprintln!("Leaving out {}", unsupported);
+        }
+        print!("{}", export.xml);
+        Ok(())
+    }
+}
+
+/// Read the nearest json-schema-catalog.toml, if any.
+fn load_config(no_config: bool) -> Result<Config> {
+    if no_config {
+        return Ok(Config::default());
+    }
+    let cwd = std::env::current_dir().context("Could not determine the current directory")?;
+    match Config::find(&cwd) {
+        Some(file) => Ok(Config::read(&file)?),
+        None => Ok(Config::default()),
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Check(cmd) => cmd.run(),
-        Commands::Lookup(cmd) => cmd.run(),
-        Commands::Replace(cmd) => cmd.run(),
+    // Only the commands that work with the selected catalogs read the
+    // configuration, so that a broken one doesn't get in the way of the others.
+    let config = || load_config(cli.no_config);
+    match &cli.command {
+        Commands::Check(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
+        Commands::Lookup(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
+        Commands::Replace(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
+        Commands::Canonicalize(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
         Commands::New(cmd) => cmd.run(),
+        Commands::Add(cmd) => cmd.run(),
+        Commands::Convert(cmd) => cmd.run(),
+        Commands::ImportSchemastore(cmd) => cmd.run(),
+        Commands::Export(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
+        Commands::Vendor(cmd) => config().and_then(|config| cmd.run(&cli.catalogs, &config)),
+        Commands::Fetch(cmd) => cmd.run(),
+        Commands::ImportXmlCatalog(cmd) => cmd.run(),
+        Commands::ExportXmlCatalog(cmd) => cmd.run(),
     }
     .unwrap_or_else(|e| {
         // ANSI bold red
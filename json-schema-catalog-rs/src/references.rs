@@ -0,0 +1,389 @@
+//! Finding the references in schema documents.
+//!
+//! A keyword such as `$ref` is only a reference where a schema is expected. Inside
+//! `properties` it is the name of a property, and inside `const`, `enum`, `examples`
+//! or `default` it is just data. [`find_references`] follows the subschema keywords
+//! of the draft that the document declares in `$schema`, so that it only finds the
+//! actual references. A reference keyword in a schema position whose value is not
+//! a string is an error.
+
+use crate::error::{Error, Result};
+use serde_json::Value;
+
+/// The keywords whose values are references, unless configured otherwise.
+pub const DEFAULT_KEYWORDS: [&str; 3] = ["$ref", "$dynamicRef", "$recursiveRef"];
+
+/// What kind of document references are looked for in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DocumentKind {
+    /// A JSON Schema. Only schema positions are visited.
+    Schema,
+    /// An OpenAPI description, in which Reference Objects can occur almost anywhere.
+    /// Everything is visited, except for example and default values, and the keys
+    /// of `properties` and similar maps are taken to be names.
+    OpenApi,
+    /// Any JSON document. Every occurrence of a keyword is a reference, as long as
+    /// its value is a string.
+    Any,
+}
+impl DocumentKind {
+    /// `OpenApi` for documents with an `openapi` or `swagger` version, and `Schema`
+    /// otherwise.
+    pub fn detect(document: &Value) -> DocumentKind {
+        if document.get("openapi").is_some() || document.get("swagger").is_some() {
+            DocumentKind::OpenApi
+        } else {
+            DocumentKind::Schema
+        }
+    }
+}
+impl std::str::FromStr for DocumentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "schema" => Ok(DocumentKind::Schema),
+            "openapi" => Ok(DocumentKind::OpenApi),
+            "any" => Ok(DocumentKind::Any),
+            _ => Err(format!(
+                "Unknown document kind {}, expected one of schema, openapi, any",
+                s
+            )),
+        }
+    }
+}
+
+/// A reference found by [`find_references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference<'a> {
+    pub keyword: &'a str,
+    pub value: &'a str,
+    /// The JSON Pointer to the value, for use with [`Value::pointer_mut`].
+    pub pointer: String,
+}
+
+/// The JSON Schema drafts, as far as the keywords that contain subschemas differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Draft {
+    /// Draft 4 up to draft 7
+    Draft7,
+    Draft2019,
+    Draft2020,
+    /// No known `$schema`: the keywords of all drafts are recognized.
+    Unknown,
+}
+impl Draft {
+    fn from_schema(schema: &str) -> Option<Draft> {
+        if schema.contains("/draft-0") {
+            Some(Draft::Draft7)
+        } else if schema.contains("/draft/2019-09/") {
+            Some(Draft::Draft2019)
+        } else if schema.contains("/draft/2020-12/") {
+            Some(Draft::Draft2020)
+        } else {
+            None
+        }
+    }
+}
+
+/// How a keyword contains subschemas.
+enum Subschemas {
+    One,
+    Array,
+    /// A subschema or an array of subschemas, like `items` before 2020-12.
+    OneOrArray,
+    /// An object with subschemas as values.
+    Map,
+}
+
+fn subschemas(draft: Draft, keyword: &str) -> Option<Subschemas> {
+    use Draft::*;
+    let shape = match keyword {
+        "additionalProperties" | "not" | "propertyNames" | "contains" | "if" | "then" | "else" => {
+            Subschemas::One
+        }
+        "allOf" | "anyOf" | "oneOf" => Subschemas::Array,
+        // The values of dependencies may also be arrays of property names, which are skipped
+        "properties" | "patternProperties" | "definitions" | "dependencies" => Subschemas::Map,
+        "additionalItems" if draft != Draft2020 => Subschemas::One,
+        "items" if draft == Draft2020 => Subschemas::One,
+        "items" => Subschemas::OneOrArray,
+        "$defs" | "dependentSchemas" if draft != Draft7 => Subschemas::Map,
+        "unevaluatedItems" | "unevaluatedProperties" | "contentSchema" if draft != Draft7 => {
+            Subschemas::One
+        }
+        "prefixItems" if draft == Draft2020 || draft == Unknown => Subschemas::Array,
+        _ => return None,
+    };
+    Some(shape)
+}
+
+/// Keys whose values are data in OpenAPI documents.
+const OPENAPI_DATA: [&str; 4] = ["const", "default", "enum", "example"];
+
+/// Keys whose values map names to schemas in OpenAPI documents.
+const OPENAPI_MAPS: [&str; 5] = [
+    "properties",
+    "patternProperties",
+    "definitions",
+    "$defs",
+    "dependentSchemas",
+];
+
+/// Find the references in a document: the string values of `keywords` in the
+/// positions that `kind` allows.
+///
+/// In a [`DocumentKind::Schema`], the keywords must have string values, and
+/// [`Error::NonStringReference`] is returned for the first one that hasn't. In the
+/// other kinds, such values are skipped, as they may well be data.
+pub fn find_references<'a>(
+    document: &'a Value,
+    kind: DocumentKind,
+    keywords: &[String],
+) -> Result<Vec<Reference<'a>>> {
+    let mut finder = Finder {
+        keywords,
+        pointer: String::new(),
+        result: vec![],
+        strict: kind == DocumentKind::Schema,
+        error: None,
+    };
+    match kind {
+        DocumentKind::Schema => finder.schema(document, Draft::Unknown),
+        DocumentKind::OpenApi => finder.openapi(document),
+        DocumentKind::Any => finder.any(document),
+    }
+    match finder.error {
+        Some(error) => Err(error),
+        None => Ok(finder.result),
+    }
+}
+
+struct Finder<'a, 'k> {
+    keywords: &'k [String],
+    pointer: String,
+    result: Vec<Reference<'a>>,
+    /// Whether keywords with other values than strings are errors.
+    strict: bool,
+    error: Option<Error>,
+}
+impl<'a> Finder<'a, '_> {
+    /// Visit `value` under `key`, with the pointer extended accordingly.
+    fn visit(&mut self, key: &str, value: &'a Value, f: impl FnOnce(&mut Self, &'a Value)) {
+        let len = self.pointer.len();
+        self.pointer.push('/');
+        self.pointer
+            .push_str(&key.replace('~', "~0").replace('/', "~1"));
+        f(self, value);
+        self.pointer.truncate(len);
+    }
+
+    /// Record `value` if `key` is a reference keyword and `value` a string. Returns
+    /// whether it was, or, if strict, whether `key` is a reference keyword.
+    fn reference(&mut self, key: &'a str, value: &'a Value) -> bool {
+        if !self.keywords.iter().any(|k| k == key) {
+            return false;
+        }
+        let mut pointer = self.pointer.clone();
+        pointer.push('/');
+        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        let Value::String(reference) = value else {
+            if self.strict && self.error.is_none() {
+                self.error = Some(Error::NonStringReference {
+                    keyword: key.to_string(),
+                    pointer,
+                    value: value.clone(),
+                });
+            }
+            return self.strict;
+        };
+        self.result.push(Reference {
+            keyword: key,
+            value: reference,
+            pointer,
+        });
+        true
+    }
+
+    fn schema(&mut self, value: &'a Value, draft: Draft) {
+        let Value::Object(map) = value else {
+            return;
+        };
+        let draft = map
+            .get("$schema")
+            .and_then(Value::as_str)
+            .and_then(Draft::from_schema)
+            .unwrap_or(draft);
+        for (key, value) in map {
+            if self.reference(key, value) {
+                continue;
+            }
+            match (subschemas(draft, key), value) {
+                (Some(Subschemas::One | Subschemas::OneOrArray), Value::Object(_)) => {
+                    self.visit(key, value, |s, v| s.schema(v, draft))
+                }
+                (Some(Subschemas::Array | Subschemas::OneOrArray), Value::Array(items)) => self
+                    .visit(key, value, |s, _| {
+                        for (i, item) in items.iter().enumerate() {
+                            s.visit(&i.to_string(), item, |s, v| s.schema(v, draft));
+                        }
+                    }),
+                (Some(Subschemas::Map), Value::Object(entries)) => {
+                    self.visit(key, value, |s, _| {
+                        for (name, entry) in entries {
+                            s.visit(name, entry, |s, v| s.schema(v, draft));
+                        }
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn openapi(&mut self, value: &'a Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    if self.reference(key, value) || OPENAPI_DATA.contains(&key.as_str()) {
+                        continue;
+                    }
+                    match value {
+                        // Example Objects, as opposed to the examples of a schema
+                        Value::Object(entries)
+                            if OPENAPI_MAPS.contains(&key.as_str()) || key == "examples" =>
+                        {
+                            self.visit(key, value, |s, _| {
+                                for (name, entry) in entries {
+                                    s.visit(name, entry, |s, v| s.openapi(v));
+                                }
+                            })
+                        }
+                        Value::Array(_) if key == "examples" => {}
+                        _ => self.visit(key, value, |s, v| s.openapi(v)),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.visit(&i.to_string(), item, |s, v| s.openapi(v));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn any(&mut self, value: &'a Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    if !self.reference(key, value) {
+                        self.visit(key, value, |s, v| s.any(v));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.visit(&i.to_string(), item, |s, v| s.any(v));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn found(document: &Value, kind: DocumentKind) -> Vec<(&str, String)> {
+        let keywords = DEFAULT_KEYWORDS.map(String::from);
+        find_references(document, kind, &keywords)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.value, r.pointer))
+            .collect()
+    }
+
+    #[test]
+    fn schema_positions() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "properties": {
+                "$ref": { "$ref": "a.json" },
+                "b": { "const": { "$ref": "not-a-reference.json" } }
+            },
+            "prefixItems": [{ "$dynamicRef": "#meta" }],
+            "examples": [{ "$ref": "not-a-reference.json" }],
+            "$defs": {
+                "old": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "$defs": { "x": { "$ref": "not-a-keyword.json" } },
+                    "items": [{ "$ref": "c/d~e.json" }]
+                }
+            }
+        });
+        assert_eq!(
+            found(&schema, DocumentKind::Schema),
+            vec![
+                ("c/d~e.json", "/$defs/old/items/0/$ref".to_string()),
+                ("#meta", "/prefixItems/0/$dynamicRef".to_string()),
+                ("a.json", "/properties/$ref/$ref".to_string()),
+            ]
+        );
+        assert_eq!(found(&schema, DocumentKind::Any).len(), 6);
+
+        let mut schema = schema;
+        *schema.pointer_mut("/$defs/old/items/0/$ref").unwrap() = json!("replaced");
+        assert_eq!(schema["$defs"]["old"]["items"][0]["$ref"], "replaced");
+    }
+
+    #[test]
+    fn non_string_references() {
+        let schema = json!({
+            "properties": {
+                "a": { "$ref": 42 },
+                "b": { "const": { "$ref": 42 } }
+            }
+        });
+        let keywords = DEFAULT_KEYWORDS.map(String::from);
+        let err = find_references(&schema, DocumentKind::Schema, &keywords).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected a string for $ref at /properties/a/$ref, but got 42"
+        );
+        assert!(found(&schema, DocumentKind::Any).is_empty());
+    }
+
+    #[test]
+    fn openapi() {
+        let document = json!({
+            "openapi": "3.1.0",
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": { "200": { "$ref": "responses.yaml#/ok" } }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Ref": {
+                        "properties": { "$ref": { "type": "string" } },
+                        "example": { "$ref": "not-a-reference.json" }
+                    }
+                },
+                "examples": { "a": { "$ref": "examples.yaml#/a" } }
+            }
+        });
+        assert_eq!(DocumentKind::detect(&document), DocumentKind::OpenApi);
+        assert_eq!(
+            found(&document, DocumentKind::OpenApi)
+                .into_iter()
+                .map(|(value, _)| value)
+                .collect::<Vec<_>>(),
+            vec!["examples.yaml#/a", "responses.yaml#/ok"]
+        );
+    }
+}
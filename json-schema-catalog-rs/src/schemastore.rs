@@ -0,0 +1,221 @@
+//! Interoperability with [SchemaStore](https://www.schemastore.org)-style `catalog.json` files.
+//!
+//! A SchemaStore catalog lists schemas by their download `url`, together with the
+//! file patterns that editors should associate them with.
+
+use crate::error::{Error, Result};
+use crate::{
+    catalog_from_groups, format, path_to_location, schema_id, Catalog, CatalogGroup, Schema,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The `$schema` of SchemaStore catalog files.
+pub const SCHEMASTORE_CATALOG_SCHEMA: &str = "https://json.schemastore.org/schema-catalog.json";
+
+/// A SchemaStore `catalog.json` file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SchemaStoreCatalog {
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub schemas: Vec<SchemaStoreEntry>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+/// An entry in a SchemaStore catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SchemaStoreEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub url: String,
+    #[serde(rename = "fileMatch", default, skip_serializing_if = "Vec::is_empty")]
+    pub file_match: Vec<String>,
+    /// Alternative versions of the schema, by version name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub versions: BTreeMap<String, String>,
+}
+
+impl SchemaStoreEntry {
+    /// The main `url` followed by the `versions` urls.
+    pub fn urls(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.url).chain(self.versions.values())
+    }
+}
+
+/// A SchemaStore entry url for which no local schema file was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingSchema {
+    pub name: String,
+    pub url: String,
+}
+
+/// The result of [`import`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub catalog: Catalog,
+    pub missing: Vec<MissingSchema>,
+}
+
+impl SchemaStoreCatalog {
+    /// Read a SchemaStore catalog in any of the supported [`format::DocumentFormat`]s.
+    pub fn read(file: &Path) -> Result<SchemaStoreCatalog> {
+        let (_format, value) = format::read_document(file)?;
+        serde_json::from_value(value).map_err(|source| Error::InvalidSchemaStoreCatalog {
+            path: file.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Find the local copy of a schema url in a directory of downloaded schemas.
+///
+/// The following locations are tried, in order, relative to `schemas_dir`:
+/// - `<host>/<path>`, as produced by mirroring tools
+/// - `<path>`
+/// - the last segment of the path
+///
+/// The result is relative to `schemas_dir`.
+pub fn find_local_schema(schemas_dir: &Path, url: &str) -> Option<PathBuf> {
+    let url = url::Url::parse(url).ok()?;
+    let segments = url
+        .path_segments()?
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            percent_encoding::percent_decode_str(s)
+                .decode_utf8()
+                .ok()
+                .filter(|s| s != "..")
+                .map(|s| PathBuf::from(s.as_ref()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let path: PathBuf = segments.iter().collect();
+    let mut candidates = vec![];
+    if let Some(host) = url.host_str() {
+        candidates.push(Path::new(host).join(&path));
+    }
+    candidates.push(path);
+    if let Some(last) = segments.last() {
+        candidates.push(last.clone());
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| schemas_dir.join(candidate).is_file())
+}
+
+/// Create a catalog from a SchemaStore catalog and a directory of downloaded schemas.
+///
+/// Each entry becomes a group named after the entry. Schemas are recorded by their
+/// `$id`, or by their url if they don't declare one. `base_location` is used as the
+/// `baseLocation` of the groups and should point to `schemas_dir`, relative to where
+/// the catalog will be written.
+pub fn import(
+    store: &SchemaStoreCatalog,
+    schemas_dir: &Path,
+    base_location: &str,
+    name: String,
+) -> Result<Import> {
+    let mut groups = vec![];
+    let mut missing = vec![];
+    for entry in &store.schemas {
+        let mut schemas = vec![];
+        for url in entry.urls() {
+            let Some(location) = find_local_schema(schemas_dir, url) else {
+                missing.push(MissingSchema {
+                    name: entry.name.clone(),
+                    url: url.clone(),
+                });
+                continue;
+            };
+            let (_format, value) =
+                format::read_document(&schemas_dir.join(&location)).map_err(|e| {
+                    Error::InSchemaStoreEntry {
+                        name: entry.name.clone(),
+                        source: Box::new(e),
+                    }
+                })?;
+            let id = schema_id(&value).unwrap_or(url).to_string();
+            schemas.push(Schema {
+                id,
+                integrity: None,
+                location: path_to_location(&location)?,
+            });
+        }
+        if !schemas.is_empty() {
+            groups.push(CatalogGroup {
+                name: entry.name.clone(),
+                base_location: base_location.to_string(),
+                schemas,
+                uri_prefix: None,
+            });
+        }
+    }
+    let catalog = catalog_from_groups(name, groups)?;
+    Ok(Import { catalog, missing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_example() {
+        let store = SchemaStoreCatalog::read(Path::new("test/schemastore/catalog.json")).unwrap();
+        let import = import(
+            &store,
+            Path::new("test/schemastore/schemas"),
+            "schemas",
+            "SchemaStore".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            import.missing,
+            vec![MissingSchema {
+                name: "Missing".to_string(),
+                url: "https://json.schemastore.org/missing.json".to_string(),
+            }]
+        );
+        assert_eq!(
+            import.catalog,
+            Catalog {
+                name: "SchemaStore".to_string(),
+                include: vec![],
+                groups: vec![
+                    CatalogGroup {
+                        name: "Example".to_string(),
+                        base_location: "schemas".to_string(),
+                        schemas: vec![
+                            Schema {
+                                id: "https://json.schemastore.org/example-1.0.json".to_string(),
+                                integrity: None,
+                                location: "json.schemastore.org/example-1.0.json".to_string(),
+                            },
+                            Schema {
+                                id: "https://schema.example.com/example.json".to_string(),
+                                integrity: None,
+                                location: "example.json".to_string(),
+                            },
+                        ],
+                        uri_prefix: None,
+                    },
+                    CatalogGroup {
+                        name: "Nested".to_string(),
+                        base_location: "schemas".to_string(),
+                        schemas: vec![Schema {
+                            id: "https://raw.example.com/nested/v1/schema.json".to_string(),
+                            integrity: None,
+                            location: "nested/v1/schema.json".to_string(),
+                        }],
+                        uri_prefix: None,
+                    },
+                ],
+            }
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Conversions between file paths and URI references.
+
+use percent_encoding::{AsciiSet, CONTROLS};
+use std::path::PathBuf;
+
+/// Characters that have to be percent-encoded when writing a path as a URI reference.
+const URI_PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Write a relative path as a URI reference.
+pub(crate) fn path_to_uri(path: &str) -> String {
+    percent_encoding::utf8_percent_encode(path, URI_PATH).to_string()
+}
+
+/// Convert a URI reference to a path, if it is relative or a `file:` URL.
+///
+/// A one-letter scheme is a Windows drive letter, as in `C:\schemas\a.json`. Such
+/// a location is not a URI, so it is taken as a path as it is, without percent-decoding.
+pub(crate) fn uri_to_path(uri: &str) -> Result<PathBuf, String> {
+    match url::Url::parse(uri) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|()| format!("{} is not a valid file path", uri)),
+        Ok(url) if url.scheme().len() == 1 => Ok(PathBuf::from(uri)),
+        Ok(url) => Err(format!("URI scheme {} is not supported", url.scheme())),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let path = uri.split(['?', '#']).next().unwrap_or_default();
+            let path = percent_encoding::percent_decode_str(path)
+                .decode_utf8()
+                .map_err(|e| format!("{} is not valid UTF-8: {}", uri, e))?;
+            Ok(PathBuf::from(path.as_ref()))
+        }
+        Err(e) => Err(format!("{} is not a valid URI: {}", uri, e)),
+    }
+}
+
+/// The form of an id that the index stores and looks up.
+///
+/// Absolute URLs are parsed, which lowercases the scheme and host, drops default
+/// ports and resolves dot-segments. On top of that, the fragment is removed, as it
+/// points into a document rather than naming one, and percent-encoding is normalized.
+/// Other ids are used as they are.
+pub(crate) fn normalize_id(id: &str) -> String {
+    match url::Url::parse(id) {
+        Ok(url) => normalize_url(&url),
+        Err(_) => id.to_string(),
+    }
+}
+
+/// See [`normalize_id`].
+pub(crate) fn normalize_url(url: &url::Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    if !url.cannot_be_a_base() {
+        let path = normalize_percent_encoding(url.path());
+        url.set_path(&path);
+    }
+    if let Some(query) = url.query() {
+        let query = normalize_percent_encoding(query);
+        url.set_query(Some(&query));
+    }
+    url.to_string()
+}
+
+/// Decode percent-encoded unreserved characters, and write the hex digits of the
+/// remaining escapes in uppercase, as RFC 3986 section 6.2.2 recommends.
+fn normalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(b) if b.is_ascii_alphanumeric() || b"-._~".contains(&b) => {
+                result.push(b as char);
+                i += 3;
+            }
+            Some(b) => {
+                result.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                // Copy the whole character, which may be more than one byte
+                let c = s[i..].chars().next().unwrap();
+                result.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,527 @@
+//! Copy schemas and the schemas they reference into a self-contained directory.
+//!
+//! The schemas are found through an [`Index`], or downloaded by [`crate::fetch`],
+//! and every `$ref` in them is followed as far as possible. The files are laid out by their id, as
+//! `<host>/<path>`, so that the result doesn't depend on where the schemas came from.
+//! Ids that only differ in their scheme or query would share a file that way, so
+//! all but the first of them get a hash of the id added to the file name.
+
+use crate::error::{Error, Result};
+use crate::format::DocumentFormat;
+use crate::references::{find_references, DocumentKind, DEFAULT_KEYWORDS};
+use crate::{
+    catalog_from_groups, integrity, path_to_location, schema_id, uri, Catalog, CatalogGroup, Index,
+    Schema,
+};
+use sha2::Digest;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+/// A reference to a schema that could not be found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// The `$ref` value, as written.
+    pub reference: String,
+    /// The id of the schema that contains the reference.
+    pub from: String,
+    /// Why the schema could not be obtained, if it was not simply missing, such as
+    /// a failed download.
+    pub reason: Option<String>,
+}
+
+/// The result of [`vendor`] and [`crate::fetch::fetch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vendored {
+    /// A catalog of the copied files, to be written into the output directory.
+    pub catalog: Catalog,
+    pub unresolved: Vec<Unresolved>,
+}
+
+/// Copy the schemas with the given ids, and those they reference transitively, into
+/// `out_dir`.
+///
+/// References are resolved against the `$id` of the document that contains them,
+/// or the id it was found by. Nested `$id`s in subschemas are not taken into
+/// account. References that the index can't resolve are returned in
+/// [`Vendored::unresolved`].
+///
+/// The catalog has a single group with `baseLocation` `.` and records the
+/// integrity of each file, so it should be written into `out_dir`.
+pub fn vendor<'a>(
+    index: &Index,
+    ids: impl IntoIterator<Item = &'a str>,
+    out_dir: &Path,
+    name: String,
+) -> Result<Vendored> {
+    copy_closure(
+        |url| {
+            let Some(path) = index.try_resolve(url)? else {
+                return Ok(None);
+            };
+            let content = std::fs::read(&path).map_err(|source| Error::Read {
+                path: path.clone(),
+                source,
+            })?;
+            Ok(Some(Document { content, path }))
+        },
+        ids,
+        out_dir,
+        name,
+    )
+}
+
+/// A schema document, as obtained by [`copy_closure`].
+pub(crate) struct Document {
+    pub content: Vec<u8>,
+    /// Where the document came from. Its extension determines the format, if it
+    /// is a known one.
+    pub path: PathBuf,
+}
+
+/// Copy documents and the documents they reference, as obtained by `get`, into
+/// `out_dir`. See [`vendor`].
+///
+/// `get` returns `None` for documents that don't exist. If a download fails for a
+/// referenced document, the reference is unresolved; only the documents with the
+/// given ids have to be obtained.
+pub(crate) fn copy_closure<'a>(
+    mut get: impl FnMut(&url::Url) -> Result<Option<Document>>,
+    ids: impl IntoIterator<Item = &'a str>,
+    out_dir: &Path,
+    name: String,
+) -> Result<Vendored> {
+    // The URLs to copy, with the reference that led to them
+    let mut queue = VecDeque::new();
+    for id in ids {
+        let url = url::Url::parse(id).map_err(|source| Error::InvalidId {
+            id: id.to_string(),
+            source,
+        })?;
+        queue.push_back((url, None));
+    }
+
+    let mut copied = BTreeMap::new();
+    let mut locations = Locations::default();
+    let mut missing = HashSet::new();
+    let mut unresolved = vec![];
+    while let Some((url, reference)) = queue.pop_front() {
+        let id = uri::normalize_url(&url);
+        if copied.contains_key(&id) {
+            continue;
+        }
+        let document = if missing.contains(&id) {
+            Ok(None)
+        } else {
+            get(&url)
+        };
+        let document = match (document, reference) {
+            (Ok(Some(document)), _) => document,
+            (Ok(None), None) => return Err(Error::SchemaNotFound { id }),
+            (Ok(None), Some(reference)) => {
+                missing.insert(id);
+                unresolved.push(reference);
+                continue;
+            }
+            (Err(error @ Error::Download { .. }), Some(reference)) => {
+                missing.insert(id);
+                let reason = match std::error::Error::source(&error) {
+                    Some(source) => format!("{}: {}", error, source),
+                    None => error.to_string(),
+                };
+                unresolved.push(Unresolved {
+                    reason: Some(reason),
+                    ..reference
+                });
+                continue;
+            }
+            (Err(error), _) => return Err(error),
+        };
+        let Document { content, path } = document;
+        let text = String::from_utf8_lossy(&content);
+        let (format, value) = crate::format::parse_document(&path, &text)?;
+
+        let base = schema_id(&value)
+            .and_then(|id| url::Url::parse(id).ok())
+            .unwrap_or_else(|| url.clone());
+        let keywords = DEFAULT_KEYWORDS.map(String::from);
+        let references = find_references(&value, DocumentKind::detect(&value), &keywords)?;
+        for reference in references.iter().map(|r| r.value) {
+            let unresolved_reference = Unresolved {
+                reference: reference.to_string(),
+                from: id.clone(),
+                reason: None,
+            };
+            let Ok(target) = base.join(reference) else {
+                unresolved.push(unresolved_reference);
+                continue;
+            };
+            if uri::normalize_url(&target) != id {
+                queue.push_back((target, Some(unresolved_reference)));
+            }
+        }
+
+        let location = locations.take(&id, location_for(&url, &path, format));
+        let target = out_dir.join(&location);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| Error::Write {
+                path: parent.to_path_buf(),
+                source,
+            })?;
+        }
+        std::fs::write(&target, &content).map_err(|source| Error::Write {
+            path: target.clone(),
+            source,
+        })?;
+        copied.insert(
+            id.clone(),
+            Schema {
+                id,
+                integrity: Some(integrity::compute(&content)),
+                location: path_to_location(&location)?,
+            },
+        );
+    }
+
+    let catalog = catalog_from_groups(
+        name.clone(),
+        vec![CatalogGroup {
+            name,
+            base_location: ".".to_string(),
+            schemas: copied.into_values().collect(),
+            uri_prefix: None,
+        }],
+    )?;
+    Ok(Vendored {
+        catalog,
+        unresolved,
+    })
+}
+
+/// The copies of schemas that `replace --recursive` writes into an output directory.
+///
+/// Copies are laid out as by [`vendor`], and keyed by the normalized id, so that
+/// references that spell an id differently share a copy.
+#[derive(Debug, Clone, Default)]
+pub struct Copies {
+    out_dir: PathBuf,
+    targets: HashMap<String, PathBuf>,
+    locations: Locations,
+    queue: VecDeque<PendingCopy>,
+}
+
+/// A schema to copy, from [`Copies::pop`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCopy {
+    /// The id of the schema, without a fragment.
+    pub id: url::Url,
+    /// The file that the schema is copied from.
+    pub source: PathBuf,
+    /// The file that the copy is written to.
+    pub target: PathBuf,
+}
+
+impl Copies {
+    pub fn new(out_dir: &Path) -> Self {
+        Copies {
+            out_dir: out_dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// Record that the schema with the given id is written to `target` by the
+    /// caller, so that references to it are not copied again.
+    pub fn insert(&mut self, id: &url::Url, target: &Path) {
+        let key = uri::normalize_url(id);
+        self.locations.insert(&key, target.to_path_buf());
+        self.targets
+            .entry(key)
+            .or_insert_with(|| target.to_path_buf());
+    }
+
+    /// The copy of the schema with the given id, which is found in `source`. The
+    /// copy is queued the first time that an id is seen.
+    pub fn target(&mut self, id: &url::Url, source: &Path) -> PathBuf {
+        let key = uri::normalize_url(id);
+        if let Some(target) = self.targets.get(&key) {
+            return target.clone();
+        }
+        let mut id = id.clone();
+        id.set_fragment(None);
+        let format = DocumentFormat::from_extension(source).unwrap_or(DocumentFormat::Json);
+        let target = self
+            .locations
+            .take(&key, self.out_dir.join(location_for(&id, source, format)));
+        self.targets.insert(key, target.clone());
+        self.queue.push_back(PendingCopy {
+            id,
+            source: source.to_path_buf(),
+            target: target.clone(),
+        });
+        target
+    }
+
+    /// The next copy to write. Writing it may queue more.
+    pub fn pop(&mut self) -> Option<PendingCopy> {
+        self.queue.pop_front()
+    }
+}
+
+/// The locations that are taken in an output directory, with the normalized ids
+/// of the schemas written there.
+#[derive(Debug, Clone, Default)]
+struct Locations(HashMap<PathBuf, String>);
+
+impl Locations {
+    /// Record that `location` is taken by the schema with the given id.
+    fn insert(&mut self, id: &str, location: PathBuf) {
+        self.0.entry(location).or_insert_with(|| id.to_string());
+    }
+
+    /// Take `location` for the schema with the given id, or, if another schema
+    /// already took it, the location with a hash of the id added to the file
+    /// name, before its extension.
+    fn take(&mut self, id: &str, location: PathBuf) -> PathBuf {
+        let location = match self.0.get(&location) {
+            Some(other) if other != id => {
+                let hash: String = sha2::Sha256::digest(id.as_bytes())[..4]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                let mut name = location.file_stem().unwrap_or_default().to_os_string();
+                name.push(format!("-{}", hash));
+                if let Some(extension) = location.extension() {
+                    name.push(".");
+                    name.push(extension);
+                }
+                location.with_file_name(name)
+            }
+            _ => location,
+        };
+        self.insert(id, location.clone());
+        location
+    }
+}
+
+/// Where to put the schema with the given id in the output directory.
+///
+/// The layout is `<host>/<path>`, with the scheme as the first directory for ids
+/// without a host. The query is left out, so [`Locations`] has to tell apart ids
+/// that only differ in it or in their scheme. If the file was in a format that its new name doesn't imply,
+/// the original extension is appended, so that the format can still be recognized.
+fn location_for(url: &url::Url, file: &Path, format: DocumentFormat) -> PathBuf {
+    let mut location = PathBuf::new();
+    match url.host_str() {
+        Some(host) => match url.port() {
+            Some(port) => location.push(format!("{}_{}", host, port)),
+            None => location.push(host),
+        },
+        None => location.push(url.scheme()),
+    }
+    let path = if url.cannot_be_a_base() {
+        // Such as urn:example:a, keep the whole path as a single file name
+        vec![url.path().replace([':', '/', '\\'], "_")]
+    } else {
+        url.path_segments()
+            .into_iter()
+            .flatten()
+            .map(|s| {
+                percent_encoding::percent_decode_str(s)
+                    .decode_utf8_lossy()
+                    .replace(['/', '\\'], "_")
+            })
+            .collect()
+    };
+    for segment in &path {
+        let segment = Path::new(segment);
+        if let [Component::Normal(_)] = segment.components().collect::<Vec<_>>()[..] {
+            location.push(segment);
+        }
+    }
+    if path.last().is_none_or(|s| s.is_empty()) {
+        location.push("index");
+    }
+    if DocumentFormat::from_extension(&location) != Some(format) {
+        if let Some(extension) = file.extension() {
+            let mut name = location.file_name().unwrap_or_default().to_os_string();
+            name.push(".");
+            name.push(extension);
+            location.set_file_name(name);
+        }
+    }
+    location
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn vendor_example() {
+        let mut index = Index::new();
+        let catalog = Catalog::read(Path::new("test/vendor/catalog.json")).unwrap();
+        catalog.index(Path::new("test/vendor"), &mut index);
+        let out_dir = TempDir::new("vendor");
+        let vendored = vendor(
+            &index,
+            ["https://schema.example.com/vendor/root.json"],
+            &out_dir,
+            "Vendored".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            vendored.unresolved,
+            vec![Unresolved {
+                reference: "https://unknown.example.com/missing.json".to_string(),
+                from: "https://schema.example.com/vendor/child.json".to_string(),
+                reason: None,
+            }]
+        );
+        let group = &vendored.catalog.groups[0];
+        assert_eq!(group.base_location, ".");
+        assert_eq!(
+            group
+                .schemas
+                .iter()
+                .map(|s| (s.id.as_str(), s.location.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "https://schema.example.com/other.json",
+                    "schema.example.com/other.json"
+                ),
+                (
+                    "https://schema.example.com/vendor/child.json",
+                    "schema.example.com/vendor/child.json.yaml"
+                ),
+                (
+                    "https://schema.example.com/vendor/root.json",
+                    "schema.example.com/vendor/root.json"
+                ),
+            ]
+        );
+        let catalog_file = out_dir.write(
+            "catalog.json",
+            &serde_json::to_string(&vendored.catalog).unwrap(),
+        );
+        vendored
+            .catalog
+            .check(&Default::default(), &catalog_file)
+            .unwrap();
+    }
+
+    #[test]
+    fn copies() {
+        let url = |s| url::Url::parse(s).unwrap();
+        let mut copies = Copies::new(Path::new("out"));
+        copies.insert(
+            &url("https://example.com/input.json"),
+            Path::new("out/in.json"),
+        );
+        assert_eq!(
+            copies.target(
+                &url("https://EXAMPLE.com/input.json#/a"),
+                Path::new("a.json")
+            ),
+            Path::new("out/in.json")
+        );
+        let target = copies.target(
+            &url("https://example.com/a%7e.yaml#/a"),
+            Path::new("a.yaml"),
+        );
+        assert_eq!(target, Path::new("out/example.com/a~.yaml"));
+        assert_eq!(
+            copies.target(&url("https://example.com/./a~.yaml"), Path::new("b.yaml")),
+            target
+        );
+        assert_eq!(
+            copies.pop(),
+            Some(PendingCopy {
+                id: url("https://example.com/a%7e.yaml"),
+                source: PathBuf::from("a.yaml"),
+                target,
+            })
+        );
+        assert_eq!(copies.pop(), None);
+    }
+
+    #[test]
+    fn colliding_locations() {
+        let ids = [
+            "https://example.com/x.json",
+            "http://example.com/x.json",
+            "https://example.com/x.json?v=2",
+        ];
+        let out_dir = TempDir::new("vendor-collisions");
+        let vendored = copy_closure(
+            |url| {
+                Ok(Some(Document {
+                    content: format!(r#"{{"$id": "{}"}}"#, url).into_bytes(),
+                    path: PathBuf::from("x.json"),
+                }))
+            },
+            ids,
+            &out_dir,
+            "Vendored".to_string(),
+        )
+        .unwrap();
+        let locations: HashMap<_, _> = vendored.catalog.groups[0]
+            .schemas
+            .iter()
+            .map(|s| (s.id.as_str(), s.location.as_str()))
+            .collect();
+        assert_eq!(
+            locations,
+            HashMap::from([
+                ("https://example.com/x.json", "example.com/x.json"),
+                ("http://example.com/x.json", "example.com/x-018b3253.json"),
+                (
+                    "https://example.com/x.json?v=2",
+                    "example.com/x-0c43626e.json"
+                ),
+            ])
+        );
+        let catalog_file = out_dir.write(
+            "catalog.json",
+            &serde_json::to_string(&vendored.catalog).unwrap(),
+        );
+        vendored
+            .catalog
+            .check(&Default::default(), &catalog_file)
+            .unwrap();
+
+        let url = |s| url::Url::parse(s).unwrap();
+        let mut copies = Copies::new(Path::new("out"));
+        copies.insert(&url(ids[0]), Path::new("out/example.com/x.json"));
+        assert_eq!(
+            copies.target(&url(ids[1]), Path::new("x.json")),
+            Path::new("out/example.com/x-018b3253.json")
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let url = |s| url::Url::parse(s).unwrap();
+        let json = Path::new("a.json");
+        assert_eq!(
+            location_for(
+                &url("https://example.com:8080/a/"),
+                json,
+                DocumentFormat::Json
+            ),
+            Path::new("example.com_8080/a/index.json")
+        );
+        assert_eq!(
+            location_for(&url("urn:example:a"), json, DocumentFormat::Json),
+            Path::new("urn/example_a.json")
+        );
+        assert_eq!(
+            location_for(
+                &url("https://example.com/%2E%2E/x.json"),
+                json,
+                DocumentFormat::Json
+            ),
+            Path::new("example.com/x.json")
+        );
+    }
+}
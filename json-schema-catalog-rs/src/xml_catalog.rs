@@ -0,0 +1,571 @@
+//! Interoperability with [OASIS XML Catalogs](https://www.oasis-open.org/committees/entity/spec-2001-08-06.html).
+//!
+//! Only `uri`, `rewriteURI` and `nextCatalog` entries, optionally in a `group` with
+//! an `xml:base`, map onto a [`Catalog`]. Other entries are reported as
+//! [`Unsupported`] when importing.
+//!
+//! `nextCatalog` corresponds to `include`. A chain of XML Catalogs has to be
+//! translated file by file, so the referenced files keep their names but get a
+//! `.json` extension on import, and an `.xml` extension on export.
+//!
+//! XML Catalogs don't have names, so catalog and group names are written as
+//! comments preceding the element, and read back from there. They have no place
+//! for the `integrity` of a schema either, which is reported by [`export`].
+
+use crate::error::{Error, Result};
+use crate::uri::{path_to_uri, uri_to_path};
+use crate::{catalog_from_groups, Catalog, CatalogGroup, Schema};
+use quick_xml::events::{BytesDecl, BytesStart, BytesText, Event};
+use std::path::{Path, PathBuf};
+
+/// The namespace of OASIS XML Catalog elements.
+pub const XML_CATALOG_NAMESPACE: &str = "urn:oasis:names:tc:entity:xmlns:xml:catalog";
+
+/// An XML Catalog entry that can not be expressed as a [`Catalog`] entry, or the
+/// other way around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The entry, written as an XML element on import, and as JSON on export.
+    pub entry: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.entry, self.reason)
+    }
+}
+
+/// The result of [`import`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub catalog: Catalog,
+    pub unsupported: Vec<Unsupported>,
+}
+
+/// The result of [`export`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub xml: String,
+    /// The parts of the catalog that the XML Catalog leaves out.
+    pub unsupported: Vec<Unsupported>,
+}
+
+struct Element {
+    /// The qualified name, for skipping to the end of the element.
+    qname: Vec<u8>,
+    /// The local name.
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl Element {
+    fn new(e: &BytesStart) -> Result<Self> {
+        let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+        let attributes = e
+            .attributes()
+            .map(|a| {
+                let a = a.map_err(read_xml)?;
+                Ok((
+                    String::from_utf8_lossy(a.key.as_ref()).to_string(),
+                    a.unescape_value().map_err(read_xml)?.to_string(),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Element {
+            qname: e.name().as_ref().to_vec(),
+            name,
+            attributes,
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn required_attribute(&self, name: &str) -> Result<&str> {
+        self.attribute(name)
+            .ok_or_else(|| self.invalid(&format!("the {} attribute is missing", name)))
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidXmlCatalog {
+            entry: self.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn unsupported(&self, reason: &str) -> Unsupported {
+        Unsupported {
+            entry: self.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (k, v) in &self.attributes {
+            write!(f, " {}=\"{}\"", k, v)?;
+        }
+        write!(f, ">")
+    }
+}
+
+fn read_xml(e: impl Into<quick_xml::Error>) -> Error {
+    Error::ReadXml { source: e.into() }
+}
+
+/// Resolve an `xml:base` against the base of the enclosing element.
+fn resolve_base(parent: &Path, base: &str) -> Result<PathBuf, String> {
+    let path = uri_to_path(base)?;
+    if base.ends_with('/') || path.as_os_str().is_empty() {
+        Ok(parent.join(path))
+    } else {
+        Ok(parent.join(path.parent().unwrap_or(Path::new(""))))
+    }
+}
+
+fn base_location(base: &Path) -> String {
+    let base = path_to_uri(&base.to_string_lossy());
+    let base = base.trim_end_matches('/');
+    if base.is_empty() {
+        ".".to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+/// Write a `baseLocation` or `location` in the canonical URI form, which it should
+/// already be in, apart from characters that are commonly left unescaped.
+fn location_uri(location: &str) -> String {
+    match uri_to_path(location) {
+        Ok(path) if path.is_relative() => path_to_uri(&path.to_string_lossy()),
+        _ => location.to_string(),
+    }
+}
+
+struct Group {
+    name: String,
+    base: PathBuf,
+    schemas: Vec<Schema>,
+    uri_prefix: Option<String>,
+}
+
+impl Group {
+    fn new(name: String, base: PathBuf) -> Self {
+        Group {
+            name,
+            base,
+            schemas: vec![],
+            uri_prefix: None,
+        }
+    }
+
+    fn finish(self, groups: &mut Vec<CatalogGroup>) {
+        if !self.schemas.is_empty() || self.uri_prefix.is_some() {
+            groups.push(CatalogGroup {
+                name: self.name,
+                base_location: base_location(&self.base),
+                schemas: self.schemas,
+                uri_prefix: self.uri_prefix,
+            });
+        }
+    }
+}
+
+/// Translate an XML Catalog to a [`Catalog`].
+///
+/// The catalog name is taken from `name`, or from a comment preceding the root
+/// element, or defaults to `Catalog`. Groups are named after a preceding comment,
+/// their `id` attribute, or the catalog name.
+pub fn import(xml: &str, name: Option<String>) -> Result<Import> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut catalog_name = name;
+    let mut comment: Option<String> = None;
+    let mut bases: Vec<PathBuf> = vec![];
+    let mut top: Option<Group> = None;
+    let mut group: Option<Group> = None;
+    let mut groups = vec![];
+    let mut include = vec![];
+    let mut unsupported = vec![];
+
+    loop {
+        let event = reader.read_event().map_err(read_xml)?;
+        let (e, is_empty) = match &event {
+            Event::Eof => break,
+            Event::Comment(text) => {
+                comment = Some(text.decode().map_err(read_xml)?.trim().to_string());
+                continue;
+            }
+            Event::Start(e) => (Element::new(e)?, false),
+            Event::Empty(e) => (Element::new(e)?, true),
+            Event::End(e) => {
+                match e.local_name().as_ref() {
+                    b"group" => {
+                        if let Some(g) = group.take() {
+                            g.finish(&mut groups);
+                        }
+                    }
+                    b"catalog" => {
+                        if let Some(g) = top.take() {
+                            g.finish(&mut groups);
+                        }
+                    }
+                    _ => {}
+                }
+                bases.pop();
+                comment = None;
+                continue;
+            }
+            _ => {
+                comment = None;
+                continue;
+            }
+        };
+        let preceding_comment = comment.take();
+
+        let parent_base = bases.last().cloned().unwrap_or_default();
+        let base = match e.attribute("xml:base") {
+            Some(base) => match resolve_base(&parent_base, base) {
+                Ok(base) => base,
+                Err(reason) => {
+                    unsupported.push(e.unsupported(&reason));
+                    if !is_empty {
+                        reader
+                            .read_to_end(quick_xml::name::QName(&e.qname))
+                            .map_err(read_xml)?;
+                    }
+                    continue;
+                }
+            },
+            None => parent_base,
+        };
+
+        match e.name.as_str() {
+            "catalog" => {
+                let name = catalog_name
+                    .get_or_insert_with(|| {
+                        preceding_comment.unwrap_or_else(|| "Catalog".to_string())
+                    })
+                    .clone();
+                top = Some(Group::new(name, base.clone()));
+            }
+            "group" => {
+                if group.is_some() {
+                    return Err(e.invalid("nested groups are not allowed"));
+                }
+                let name = preceding_comment
+                    .or_else(|| e.attribute("id").map(|id| id.to_string()))
+                    .or_else(|| catalog_name.clone())
+                    .unwrap_or_else(|| "Catalog".to_string());
+                group = Some(Group::new(name, base.clone()));
+            }
+            "uri" => {
+                let id = e.required_attribute("name")?;
+                let uri = e.required_attribute("uri")?;
+                match uri_to_path(uri) {
+                    Ok(location) => {
+                        let target = group
+                            .as_mut()
+                            .or(top.as_mut())
+                            .ok_or_else(|| e.invalid("not inside a <catalog> element"))?;
+                        let location = base.join(location);
+                        match location.strip_prefix(&target.base) {
+                            _ if location.is_absolute() && !target.base.is_absolute() => {
+                                unsupported.push(e.unsupported(
+                                    "absolute locations can not be represented in a group with a relative xml:base",
+                                ))
+                            }
+                            Ok(location) => target.schemas.push(Schema {
+                                id: id.to_string(),
+                                integrity: None,
+                                location: path_to_uri(&location.to_string_lossy()),
+                            }),
+                            Err(_) => unsupported.push(
+                                e.unsupported("xml:base points outside of the enclosing group"),
+                            ),
+                        }
+                    }
+                    Err(reason) => unsupported.push(e.unsupported(&reason)),
+                }
+            }
+            "nextCatalog" => {
+                let next = e.required_attribute("catalog")?;
+                match uri_to_path(next) {
+                    Ok(path) => include.push(
+                        base.join(path)
+                            .with_extension("json")
+                            .to_string_lossy()
+                            .to_string(),
+                    ),
+                    Err(reason) => unsupported.push(e.unsupported(&reason)),
+                }
+            }
+            "rewriteURI" => {
+                let prefix = e.required_attribute("uriStartString")?;
+                let rewrite = e.required_attribute("rewritePrefix")?;
+                if !rewrite.ends_with('/') {
+                    unsupported.push(e.unsupported("rewritePrefix must end with /"));
+                } else {
+                    match uri_to_path(rewrite) {
+                        Ok(dir) => {
+                            let dir = base.join(dir);
+                            let target = group
+                                .as_mut()
+                                .or(top.as_mut())
+                                .ok_or_else(|| e.invalid("not inside a <catalog> element"))?;
+                            if target.uri_prefix.is_none() && target.base == dir {
+                                target.uri_prefix = Some(prefix.to_string());
+                            } else {
+                                let mut g = Group::new(
+                                    preceding_comment.unwrap_or(target.name.clone()),
+                                    dir,
+                                );
+                                g.uri_prefix = Some(prefix.to_string());
+                                g.finish(&mut groups);
+                            }
+                        }
+                        Err(reason) => unsupported.push(e.unsupported(&reason)),
+                    }
+                }
+            }
+            _ => {
+                unsupported.push(e.unsupported("entry type is not supported"));
+                if !is_empty {
+                    reader
+                        .read_to_end(quick_xml::name::QName(&e.qname))
+                        .map_err(read_xml)?;
+                }
+                continue;
+            }
+        }
+        if !is_empty {
+            bases.push(base);
+        }
+    }
+
+    let mut catalog = catalog_from_groups(
+        catalog_name.unwrap_or_else(|| "Catalog".to_string()),
+        groups,
+    )?;
+    catalog.include = include;
+    Ok(Import {
+        catalog,
+        unsupported,
+    })
+}
+
+fn comment(text: &str) -> Event<'static> {
+    Event::Comment(BytesText::from_escaped(format!(
+        " {} ",
+        text.replace("--", "- -")
+    )))
+}
+
+/// Translate a [`Catalog`] to an XML Catalog.
+///
+/// Each group becomes a `group` element with its `baseLocation` as `xml:base`.
+/// A `uriPrefix` becomes a `rewriteURI` entry in the group, and each `include`
+/// becomes a `nextCatalog` entry for the XML Catalog exported from it.
+/// The `integrity` of schemas is left out, and reported in [`Export::unsupported`].
+pub fn export(catalog: &Catalog) -> Result<Export> {
+    let xml = write_xml(catalog).map_err(|source| Error::WriteXml { source })?;
+    let unsupported = catalog
+        .groups
+        .iter()
+        .flat_map(|group| &group.schemas)
+        .filter(|schema| schema.integrity.is_some())
+        .map(|schema| Unsupported {
+            entry: serde_json::to_string(schema).unwrap_or_default(),
+            reason: "integrity can not be represented in an XML Catalog".to_string(),
+        })
+        .collect();
+    Ok(Export { xml, unsupported })
+}
+
+fn write_xml(catalog: &Catalog) -> std::io::Result<String> {
+    let mut writer = quick_xml::Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(comment(&catalog.name))?;
+    writer
+        .create_element("catalog")
+        .with_attribute(("xmlns", XML_CATALOG_NAMESPACE))
+        .write_inner_content(|w| {
+            for include in &catalog.include {
+                w.create_element("nextCatalog")
+                    .with_attribute((
+                        "catalog",
+                        path_to_uri(&Path::new(include).with_extension("xml").to_string_lossy())
+                            .as_str(),
+                    ))
+                    .write_empty()?;
+            }
+            for group in &catalog.groups {
+                w.write_event(comment(&group.name))?;
+                let base = format!(
+                    "{}/",
+                    location_uri(group.base_location.trim_end_matches('/'))
+                );
+                w.create_element("group")
+                    .with_attribute(("xml:base", base.as_str()))
+                    .write_inner_content(|w| {
+                        for schema in &group.schemas {
+                            w.create_element("uri")
+                                .with_attribute(("name", schema.id.as_str()))
+                                .with_attribute(("uri", location_uri(&schema.location).as_str()))
+                                .write_empty()?;
+                        }
+                        if let Some(prefix) = &group.uri_prefix {
+                            w.create_element("rewriteURI")
+                                .with_attribute(("uriStartString", prefix.as_str()))
+                                .with_attribute(("rewritePrefix", "./"))
+                                .write_empty()?;
+                        }
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+    let mut xml = String::from_utf8(writer.into_inner())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    xml.push('\n');
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_example() {
+        let xml = std::fs::read_to_string("test/xml-catalog/catalog.xml").unwrap();
+        let import = import(&xml, None).unwrap();
+        assert_eq!(
+            import.catalog,
+            Catalog {
+                name: "Example XML Catalog".to_string(),
+                include: vec!["other.json".to_string()],
+                groups: vec![
+                    CatalogGroup {
+                        name: "Example XML Catalog".to_string(),
+                        base_location: ".".to_string(),
+                        schemas: vec![Schema {
+                            id: "https://schema.example.com/top.json".to_string(),
+                            integrity: None,
+                            location: "schemas/top%20level.json".to_string(),
+                        }],
+                        uri_prefix: None,
+                    },
+                    CatalogGroup {
+                        name: "vendored".to_string(),
+                        base_location: "../../vendor".to_string(),
+                        schemas: vec![Schema {
+                            id: "https://schema.example.com/schema/schema_catalog_schema.json"
+                                .to_string(),
+                            integrity: None,
+                            location: "schema_catalog_schema.json".to_string(),
+                        }],
+                        uri_prefix: None,
+                    },
+                    CatalogGroup {
+                        name: "Example XML Catalog".to_string(),
+                        base_location: "v3".to_string(),
+                        schemas: vec![],
+                        uri_prefix: Some("https://schema.example.com/v3/".to_string()),
+                    },
+                ],
+            }
+        );
+        assert_eq!(
+            import
+                .unsupported
+                .iter()
+                .map(|u| u.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "<uri name=\"https://schema.example.com/remote.json\" uri=\"https://example.com/remote.json\">: URI scheme https is not supported",
+                "<uri name=\"https://schema.example.com/absolute.json\" uri=\"file:///schemas/absolute.json\">: absolute locations can not be represented in a group with a relative xml:base",
+            ]
+        );
+    }
+
+    #[test]
+    fn import_invalid() {
+        let xml = format!(
+            "<catalog xmlns=\"{}\"><uri name=\"https://example.com/a.json\"/></catalog>",
+            XML_CATALOG_NAMESPACE
+        );
+        assert!(matches!(
+            import(&xml, None),
+            Err(Error::InvalidXmlCatalog { reason, .. }) if reason == "the uri attribute is missing"
+        ));
+        assert!(matches!(
+            import("<catalog><uri", None),
+            Err(Error::ReadXml { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trip_prefix() {
+        let catalog = Catalog {
+            name: "Prefixes".to_string(),
+            include: vec![],
+            groups: vec![CatalogGroup {
+                name: "v3".to_string(),
+                base_location: "schemas/v3".to_string(),
+                schemas: vec![Schema {
+                    id: "https://schema.example.com/other.json".to_string(),
+                    integrity: None,
+                    location: "other.json".to_string(),
+                }],
+                uri_prefix: Some("https://schema.example.com/v3/".to_string()),
+            }],
+        };
+        let xml = export(&catalog).unwrap().xml;
+        assert!(xml.contains(
+            "<rewriteURI uriStartString=\"https://schema.example.com/v3/\" rewritePrefix=\"./\"/>"
+        ));
+        assert_eq!(import(&xml, None).unwrap().catalog, catalog);
+    }
+
+    #[test]
+    fn round_trip() {
+        let catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        let export = export(&catalog).unwrap();
+        assert!(export.unsupported.is_empty());
+        let xml = export.xml;
+        assert!(xml.contains("<group xml:base=\"../vendor/\">"));
+        assert!(super::export(&Catalog {
+            include: vec!["other.yaml".to_string()],
+            ..catalog.clone()
+        })
+        .unwrap()
+        .xml
+        .contains("<nextCatalog catalog=\"other.xml\"/>"));
+        let import = import(&xml, None).unwrap();
+        assert_eq!(import.catalog, catalog);
+        assert!(import.unsupported.is_empty());
+    }
+
+    #[test]
+    fn export_integrity() {
+        let mut catalog = Catalog::read(Path::new("test/example.json")).unwrap();
+        catalog.groups[0].schemas[0].integrity = Some("sha256-abc".to_string());
+        let export = export(&catalog).unwrap();
+        assert!(!export.xml.contains("sha256-abc"));
+        assert_eq!(
+            export.unsupported,
+            vec![Unsupported {
+                entry: "{\"id\":\"https://schema.example.com/schema/schema_catalog_schema.json\",\"integrity\":\"sha256-abc\",\"location\":\"schema_catalog_schema.json\"}".to_string(),
+                reason: "integrity can not be represented in an XML Catalog".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Tests of reading `json-schema-catalog.toml`, which run the binary.
+
+mod common;
+
+use common::TempDir;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_json-schema-catalog"))
+        .current_dir(dir)
+        .env_remove("JSON_SCHEMA_CATALOGS")
+        .args(["--no-xdg", "--no-cache"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn broken_config() {
+    let dir = TempDir::new("broken-config");
+    dir.write("json-schema-catalog.toml", "catalogs = [");
+    dir.write(
+        "s.json",
+        r#"{"$id": "https://example.com/s.json", "title": "S"}"#,
+    );
+
+    // Commands that don't use the configuration don't read it
+    let output = run(&dir, &["new", "s.json"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = run(&dir, &["lookup", "https://example.com/s.json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("json-schema-catalog.toml"));
+}
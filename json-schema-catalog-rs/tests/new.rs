@@ -0,0 +1,25 @@
+//! Tests of `json-schema-catalog new`, which run the binary.
+
+use std::process::Command;
+
+fn new(args: &[&str]) -> serde_json::Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_json-schema-catalog"))
+        .args(["--no-config", "--no-xdg", "--no-cache", "new"])
+        .args(args)
+        .arg("test/vendor/schemas/other.json")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn integrity() {
+    let schema = &new(&[])["groups"][0]["schemas"][0];
+    assert!(schema["integrity"]
+        .as_str()
+        .is_some_and(|integrity| integrity.starts_with("sha256-")));
+
+    let schema = &new(&["--no-integrity"])["groups"][0]["schemas"][0];
+    assert!(schema.get("integrity").is_none());
+}
@@ -0,0 +1,84 @@
+//! The example usage from the README, with the catalogs in `test/xdg`.
+
+mod common;
+
+use common::TempDir;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str]) -> Output {
+    run_with_input(args, "")
+}
+
+fn run_with_input(args: &[&str], input: &str) -> Output {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cache = TempDir::new("readme");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_json-schema-catalog"))
+        .current_dir(root.parent().unwrap())
+        .env_remove("JSON_SCHEMA_CATALOGS")
+        .env("XDG_DATA_HOME", root.join("test/xdg/empty"))
+        .env("XDG_DATA_DIRS", root.join("test/xdg"))
+        .env("XDG_CACHE_HOME", &*cache)
+        .arg("--no-config")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+fn installed_schema() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test/xdg/json-schema-catalogs/vendor/schema_catalog_schema.json")
+}
+
+#[test]
+fn lookup() {
+    let output = run(&[
+        "lookup",
+        "https://schema.example.com/schema/schema_catalog_schema.json",
+    ]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", installed_schema().display())
+    );
+}
+
+#[test]
+fn lookup_from_standard_input() {
+    let output = run_with_input(
+        &["lookup", "--json", "--keep-going"],
+        "https://schema.example.com/schema/schema_catalog_schema.json\n",
+    );
+    let paths: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        paths["https://schema.example.com/schema/schema_catalog_schema.json"],
+        installed_schema().to_str().unwrap()
+    );
+}
+
+#[test]
+fn replace() {
+    let output = run(&[
+        "replace",
+        "json-schema-catalog-rs/test/example-with-schema.json",
+    ]);
+    let replaced: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        replaced["$schema"],
+        url::Url::from_file_path(installed_schema())
+            .unwrap()
+            .to_string()
+    );
+}
@@ -0,0 +1,209 @@
+//! Tests of `json-schema-catalog replace`, which run the binary.
+
+mod common;
+
+use common::TempDir;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const DOCUMENT: &str = "{\n  \"$ref\": \"https://example.com/s.json\"\n}\n";
+
+/// A directory with a catalog for https://example.com/s.json, and documents that
+/// reference it at `a/doc.json` and `a/b/doc.json`.
+fn project(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+    dir.write(
+        "catalog.json",
+        r#"{"name": "Test", "groups": [{"name": "Test", "baseLocation": ".", "schemas": [{"id": "https://example.com/s.json", "location": "s.json"}]}]}"#,
+    );
+    dir.write("s.json", r#"{"$id": "https://example.com/s.json"}"#);
+    dir.write("a/doc.json", DOCUMENT);
+    dir.write("a/b/doc.json", DOCUMENT);
+    dir
+}
+
+/// Run `replace` in `dir`, with the catalog of [`project`].
+fn replace(dir: &Path, args: &[&str]) -> Output {
+    replace_in(dir, dir, args)
+}
+
+fn replace_in(dir: &Path, current_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_json-schema-catalog"))
+        .current_dir(current_dir)
+        .env_remove("JSON_SCHEMA_CATALOGS")
+        .args(["--no-config", "--no-xdg", "--no-cache", "--catalog"])
+        .arg(dir.join("catalog.json"))
+        .arg("replace")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn read(path: PathBuf) -> String {
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn check_and_in_place() {
+    let dir = project("in-place");
+
+    let output = replace(&dir, &["--check", "a/doc.json", "a/b/doc.json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 file(s) would change"));
+    assert_eq!(read(dir.join("a/doc.json")), DOCUMENT);
+
+    let output = replace(&dir, &["--in-place", "a/doc.json", "a/b/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_ne!(read(dir.join("a/doc.json")), DOCUMENT);
+
+    let output = replace(&dir, &["--check", "a/doc.json", "a/b/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn output_dir() {
+    let dir = project("output-dir");
+
+    let output = replace(&dir, &["--output-dir", "out", "a/doc.json", "a/b/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("out/doc.json").is_file());
+    assert!(dir.join("out/b/doc.json").is_file());
+    assert_eq!(read(dir.join("a/doc.json")), DOCUMENT);
+
+    // Inputs outside of the current directory are mirrored too
+    let output = replace_in(
+        &dir,
+        &dir.join("a/b"),
+        &["--output-dir", "out", "../doc.json"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("a/b/out/doc.json").is_file());
+}
+
+#[test]
+fn relative() {
+    let dir = project("relative");
+
+    let output = replace(
+        &dir,
+        &["--relative", "--in-place", "a/doc.json", "a/b/doc.json"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read(dir.join("a/doc.json")),
+        "{\n  \"$ref\": \"../s.json\"\n}\n"
+    );
+    assert_eq!(
+        read(dir.join("a/b/doc.json")),
+        "{\n  \"$ref\": \"../../s.json\"\n}\n"
+    );
+
+    dir.write("a/doc.json", DOCUMENT);
+    let output = replace(&dir, &["--relative", "--output-dir", "a/out", "a/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read(dir.join("a/out/doc.json")),
+        "{\n  \"$ref\": \"../../s.json\"\n}\n"
+    );
+
+    let output = replace(&dir, &["--relative-to", "a", "a/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\n  \"$ref\": \"../s.json\"\n}\n"
+    );
+
+    let output = replace(&dir, &["--relative-to", "a", "--in-place", "a/doc.json"]);
+    assert!(!output.status.success());
+    assert_eq!(read(dir.join("a/doc.json")), DOCUMENT);
+    let output = replace(&dir, &["--relative", "a/doc.json"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn metaschemas_and_invalid_references() {
+    let dir = project("metaschemas");
+    dir.write(
+        "doc.json",
+        r#"{"$schema": "http://json-schema.org/draft-07/schema#", "$ref": "https://example.com/s.json"}"#,
+    );
+    dir.write(
+        "invalid.json",
+        r#"{"$schema": "http://json-schema.org/draft-07/schema#", "$ref": 42}"#,
+    );
+
+    // Unknown metaschemas are left alone
+    let output = replace(&dir, &["doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let replaced: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        replaced["$schema"],
+        "http://json-schema.org/draft-07/schema#"
+    );
+    assert_ne!(replaced["$ref"], "https://example.com/s.json");
+
+    let output = replace(&dir, &["--ignore-unknown", "invalid.json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Expected a string for $ref at /$ref, but got 42"));
+}
+
+#[test]
+fn warns_when_reformatting() {
+    let dir = project("reformatting");
+    dir.write(
+        "doc.toml",
+        "# comment\n\"$ref\" = \"https://example.com/s.json\"\n",
+    );
+
+    let output = replace(&dir, &["doc.toml"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not edit doc.toml in place"));
+
+    let output = replace(&dir, &["a/doc.json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn stdin_with_output_files() {
+    let dir = project("stdin");
+
+    for mode in [&["--in-place"][..], &["--output-dir", "out"]] {
+        let output = replace(&dir, &[mode, &["a/doc.json", "-"]].concat());
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Standard input"));
+        assert_eq!(read(dir.join("a/doc.json")), DOCUMENT);
+        assert!(!dir.join("out").exists());
+    }
+}
+
+#[test]
+fn recursive() {
+    let out_dir = TempDir::new("recursive");
+    let output = Command::new(env!("CARGO_BIN_EXE_json-schema-catalog"))
+        .env_remove("JSON_SCHEMA_CATALOGS")
+        .args(["--no-config", "--no-xdg", "--no-cache"])
+        .args(["--catalog", "test/vendor/catalog.json", "replace"])
+        .args(["--recursive", "--ignore-unknown", "--output-dir"])
+        .arg(&*out_dir)
+        .arg("test/vendor/schemas/root.json")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let root = read(out_dir.join("root.json"));
+    assert!(root.contains(r##""child": { "$ref": "schema.example.com/vendor/child.json.yaml" }"##));
+    assert!(root.contains(r##""local": { "$ref": "#/definitions/local" }"##));
+    assert_eq!(
+        read(out_dir.join("schema.example.com/vendor/child.json.yaml")),
+        read(PathBuf::from("test/vendor/schemas/child.yaml"))
+            .replace("$ref: root.json", "$ref: ../../root.json")
+    );
+    assert_eq!(
+        read(out_dir.join("schema.example.com/other.json")),
+        read(PathBuf::from("test/vendor/schemas/other.json"))
+    );
+    // The input is not copied again for the reference back to it
+    assert!(!out_dir.join("schema.example.com/vendor/root.json").exists());
+}
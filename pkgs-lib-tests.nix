@@ -51,6 +51,8 @@ rec {
         {
           "$id": "https://example.com/schemas/integration-test.json",
           "$schema": "https://json-schema.org/draft-07/schema#",
+          "title": "Integration Test",
+          "type": "object",
           "oneOf": [
             {
               "$ref": "file://${
@@ -65,9 +67,7 @@ rec {
             {
               "$ref": "./foo.json#/definitions/bar"
             }
-          ],
-          "title": "Integration Test",
-          "type": "object"
+          ]
         }
         EOF
         ( set -x;